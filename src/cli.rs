@@ -0,0 +1,505 @@
+use std::ffi::OsString;
+use std::fmt::{self, Write};
+
+use adw::prelude::*;
+use gtk::{gio, glib};
+use serde_json::json;
+
+use crate::config::APP_ID;
+use crate::persistence;
+use crate::quick_add;
+use crate::utils::split_date_time;
+use crate::widgets::{CollectionObject, SavedFilterObject, TaskObject};
+
+pub const USAGE: &str = "\
+Usage: todo-gtk-rs [COMMAND]
+
+Opens the window when no command is given.
+
+Commands:
+  add [--collection NAME] TEXT...    Add a task, TEXT is read like the task entry reads it
+  list [--collection NAME] [--json]  List tasks with their IDs
+  done ID...                         Mark tasks as done
+  collections [--json]               List collections with their IDs
+  help                               Show this message
+";
+
+/// Something to do from the command line instead of opening the window.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Add {
+        collection: Option<String>,
+        text: String,
+    },
+    List {
+        collection: Option<String>,
+        json: bool,
+    },
+    Done {
+        ids: Vec<String>,
+    },
+    Collections {
+        json: bool,
+    },
+    Help,
+}
+
+impl Command {
+    /// Whether running it changes what is stored.
+    fn modifies(&self) -> bool {
+        matches!(self, Command::Add { .. } | Command::Done { .. })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    UnknownCommand(String),
+    UnknownOption(String),
+    MissingValue(&'static str),
+    MissingArgument(&'static str),
+    UnexpectedArgument(String),
+    NotUnicode,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnknownCommand(command) => write!(f, "unknown command “{command}”"),
+            ParseError::UnknownOption(option) => write!(f, "unknown option “{option}”"),
+            ParseError::MissingValue(option) => write!(f, "{option} needs a value"),
+            ParseError::MissingArgument(argument) => write!(f, "missing {argument}"),
+            ParseError::UnexpectedArgument(argument) => {
+                write!(f, "unexpected argument “{argument}”")
+            }
+            ParseError::NotUnicode => write!(f, "arguments must be valid UTF-8"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses the arguments after the program name. `None` means there is no
+/// command and the window should open.
+pub fn parse(args: &[OsString]) -> Result<Option<Command>, ParseError> {
+    let args = args
+        .iter()
+        .map(|arg| arg.to_str().ok_or(ParseError::NotUnicode))
+        .collect::<Result<Vec<_>, _>>()?;
+    let Some((&command, rest)) = args.split_first() else {
+        return Ok(None);
+    };
+    // Before its options and arguments, which only make sense for known ones
+    let known = ["add", "list", "done", "collections", "help", "--help", "-h"];
+    if !known.contains(&command) {
+        return Err(ParseError::UnknownCommand(command.to_owned()));
+    }
+
+    let mut collection = None;
+    let mut json = false;
+    let mut arguments = Vec::new();
+    let mut rest = rest.iter().copied();
+    while let Some(arg) = rest.next() {
+        match arg {
+            "--collection" | "-c" => {
+                let name = rest
+                    .next()
+                    .ok_or(ParseError::MissingValue("--collection"))?;
+                collection = Some(name.to_owned());
+            }
+            "--json" => json = true,
+            // Everything after "--" is text, even if it starts with "-"
+            "--" => arguments.extend(rest.by_ref()),
+            _ => match arg.strip_prefix("--collection=") {
+                Some(name) => collection = Some(name.to_owned()),
+                None if arg.starts_with('-') && arg.len() > 1 => {
+                    return Err(ParseError::UnknownOption(arg.to_owned()));
+                }
+                None => arguments.push(arg),
+            },
+        }
+    }
+
+    let takes_collection = matches!(command, "add" | "list");
+    let takes_json = matches!(command, "list" | "collections");
+    if collection.is_some() && !takes_collection {
+        return Err(ParseError::UnknownOption("--collection".to_owned()));
+    }
+    if json && !takes_json {
+        return Err(ParseError::UnknownOption("--json".to_owned()));
+    }
+
+    let takes_arguments = matches!(command, "add" | "done");
+    if let Some(argument) = arguments.first().filter(|_| !takes_arguments) {
+        return Err(ParseError::UnexpectedArgument((*argument).to_owned()));
+    }
+
+    let command = match command {
+        "add" if arguments.is_empty() => return Err(ParseError::MissingArgument("TEXT")),
+        "add" => Command::Add {
+            collection,
+            text: arguments.join(" "),
+        },
+        "list" => Command::List { collection, json },
+        "done" if arguments.is_empty() => return Err(ParseError::MissingArgument("ID")),
+        "done" => Command::Done {
+            ids: arguments.into_iter().map(str::to_owned).collect(),
+        },
+        "collections" => Command::Collections { json },
+        "help" | "--help" | "-h" => Command::Help,
+        _ => unreachable!("Unknown commands are rejected first"),
+    };
+    Ok(Some(command))
+}
+
+/// Runs `command` on the collections in the store, returning what to print.
+/// Changes are left to whoever owns the store to save.
+pub fn run(command: &Command, collections: &gio::ListStore) -> Result<String, String> {
+    match command {
+        Command::Add { collection, text } => add(collections, collection.as_deref(), text),
+        Command::List { collection, json } => list(collections, collection.as_deref(), *json),
+        Command::Done { ids } => done(collections, ids),
+        Command::Collections { json } => Ok(list_collections(collections, *json)),
+        Command::Help => Ok(USAGE.to_owned()),
+    }
+}
+
+/// Runs `command` straight on the data file, for when no window owns it.
+pub fn run_on_data_file(command: &Command) -> Result<String, String> {
+    let data = persistence::load().map_err(|err| err.to_string())?;
+
+    let collections = gio::ListStore::new::<CollectionObject>();
+    collections.extend_from_slice(
+        &data
+            .collections
+            .into_iter()
+            .map(CollectionObject::from_collection_data)
+            .collect::<Vec<_>>(),
+    );
+    let saved_filters = gio::ListStore::new::<SavedFilterObject>();
+    saved_filters.extend_from_slice(
+        &data
+            .saved_filters
+            .into_iter()
+            .map(SavedFilterObject::from_saved_filter_data)
+            .collect::<Vec<_>>(),
+    );
+
+    let output = run(command, &collections)?;
+
+    if command.modifies() {
+        // Recurring tasks roll forward from an idle callback, let that happen first
+        let context = glib::MainContext::default();
+        while context.iteration(false) {}
+
+        persistence::save(&collections, &saved_filters)
+            .map_err(|err| format!("could not save tasks: {err}"))?;
+    }
+
+    Ok(output)
+}
+
+fn add(
+    collections: &gio::ListStore,
+    collection: Option<&str>,
+    text: &str,
+) -> Result<String, String> {
+    let collection = match collection {
+        Some(name) => find_collection(collections, name)
+            .ok_or_else(|| format!("no collection is called “{name}”"))?,
+        None => default_collection(collections)
+            .ok_or_else(|| "there is no collection to add the task to".to_owned())?,
+    };
+
+    let now = glib::DateTime::now_local().expect("Could not get the local time");
+    let (today, now) = split_date_time(&now);
+    let task =
+        TaskObject::from_quick_add(text.to_owned(), quick_add::parse(text, today, now), false);
+    collection.tasks().append(&task);
+
+    Ok(format!("{}\n", task.id()))
+}
+
+fn list(
+    collections: &gio::ListStore,
+    collection: Option<&str>,
+    json: bool,
+) -> Result<String, String> {
+    let listed = match collection {
+        Some(name) => vec![find_collection(collections, name)
+            .ok_or_else(|| format!("no collection is called “{name}”"))?],
+        None => collections
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .collect(),
+    };
+
+    if json {
+        let data = listed
+            .iter()
+            .map(CollectionObject::to_collection_data)
+            .collect::<Vec<_>>();
+        let json = serde_json::to_string_pretty(&data).map_err(|err| err.to_string())?;
+        return Ok(json + "\n");
+    }
+
+    let mut output = String::new();
+    for collection in listed {
+        writeln!(output, "{}", collection.title()).unwrap();
+        for task in collection
+            .tasks()
+            .iter::<TaskObject>()
+            .filter_map(Result::ok)
+        {
+            writeln!(output, "  {}", task_line(&task)).unwrap();
+        }
+    }
+    Ok(output)
+}
+
+/// Like "[ ] 0b6c…  Pay rent  due 2026-11-01 09:00 #home".
+fn task_line(task: &TaskObject) -> String {
+    let check = if task.is_completed() { 'x' } else { ' ' };
+    let mut line = format!("[{check}] {}  {}", task.id(), task.content());
+    if let Some((date, time)) = task.due() {
+        write!(line, "  due {date}").unwrap();
+        if let Some(time) = time {
+            write!(line, " {time}").unwrap();
+        }
+    }
+    for tag in task.tags() {
+        write!(line, " #{tag}").unwrap();
+    }
+    line
+}
+
+fn done(collections: &gio::ListStore, ids: &[String]) -> Result<String, String> {
+    // Check every ID first, so a typo doesn't leave half of them done
+    let tasks = ids
+        .iter()
+        .map(|id| {
+            collections
+                .iter::<CollectionObject>()
+                .filter_map(Result::ok)
+                .find_map(|collection| collection.find_task(id))
+                .ok_or_else(|| format!("no task has the ID “{id}”"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for task in tasks {
+        task.set_completed(true);
+    }
+    Ok(String::new())
+}
+
+fn list_collections(collections: &gio::ListStore, json: bool) -> String {
+    let collections = collections
+        .iter::<CollectionObject>()
+        .filter_map(Result::ok)
+        .map(|collection| {
+            let tasks = collection.tasks();
+            let open = tasks
+                .iter::<TaskObject>()
+                .filter_map(Result::ok)
+                .filter(|task| !task.is_completed())
+                .count();
+            (collection, open, tasks.n_items())
+        })
+        .collect::<Vec<_>>();
+
+    if json {
+        let data = collections
+            .iter()
+            .map(|(collection, open, total)| {
+                json!({
+                    "id": collection.id(),
+                    "title": collection.title(),
+                    "open": open,
+                    "total": total,
+                })
+            })
+            .collect::<Vec<_>>();
+        return serde_json::Value::from(data).to_string() + "\n";
+    }
+
+    collections
+        .iter()
+        .map(|(collection, open, total)| {
+            format!(
+                "{}  {} ({open} of {total} open)\n",
+                collection.id(),
+                collection.title()
+            )
+        })
+        .collect()
+}
+
+/// Looks a collection up by ID, or by title ignoring case.
+pub fn find_collection(collections: &gio::ListStore, name: &str) -> Option<CollectionObject> {
+    let name = name.to_lowercase();
+    collections
+        .iter::<CollectionObject>()
+        .filter_map(Result::ok)
+        .find(|collection| collection.id() == name || collection.title().to_lowercase() == name)
+}
+
+/// The collection picked for new tasks in the window, or the first one.
+pub fn default_collection(collections: &gio::ListStore) -> Option<CollectionObject> {
+    let id = gio::Settings::new(APP_ID).string("default-collection");
+    collections
+        .iter::<CollectionObject>()
+        .filter_map(Result::ok)
+        .find(|collection| collection.id() == id.as_str())
+        .or_else(|| collections.item(0).and_downcast())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Option<Command>, ParseError> {
+        parse(&args.iter().map(OsString::from).collect::<Vec<_>>())
+    }
+
+    fn add(collection: Option<&str>, text: &str) -> Option<Command> {
+        Some(Command::Add {
+            collection: collection.map(str::to_owned),
+            text: text.to_owned(),
+        })
+    }
+
+    #[test]
+    fn no_command_opens_the_window() {
+        assert_eq!(parse_args(&[]), Ok(None));
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(
+            parse_args(&["add", "Pay", "rent"]),
+            Ok(add(None, "Pay rent"))
+        );
+        assert_eq!(
+            parse_args(&["list", "--json"]),
+            Ok(Some(Command::List {
+                collection: None,
+                json: true
+            }))
+        );
+        assert_eq!(
+            parse_args(&["done", "a", "b"]),
+            Ok(Some(Command::Done {
+                ids: vec!["a".to_owned(), "b".to_owned()]
+            }))
+        );
+        assert_eq!(
+            parse_args(&["collections"]),
+            Ok(Some(Command::Collections { json: false }))
+        );
+        for help in ["help", "--help", "-h"] {
+            assert_eq!(parse_args(&[help]), Ok(Some(Command::Help)));
+        }
+    }
+
+    #[test]
+    fn collections() {
+        let expected = add(Some("Home Office"), "Call Bob");
+        assert_eq!(
+            parse_args(&["add", "--collection=Home Office", "Call", "Bob"]),
+            Ok(expected.clone())
+        );
+        assert_eq!(
+            parse_args(&["add", "-c", "Home Office", "Call", "Bob"]),
+            Ok(expected.clone())
+        );
+        assert_eq!(
+            parse_args(&["add", "Call", "--collection", "Home Office", "Bob"]),
+            Ok(expected)
+        );
+        assert_eq!(
+            parse_args(&["list", "-c", "work"]),
+            Ok(Some(Command::List {
+                collection: Some("work".to_owned()),
+                json: false
+            }))
+        );
+    }
+
+    #[test]
+    fn text_after_double_dash() {
+        assert_eq!(
+            parse_args(&["add", "--", "-5", "degrees", "--json", "-c", "x"]),
+            Ok(add(None, "-5 degrees --json -c x"))
+        );
+        assert_eq!(
+            parse_args(&["add", "-c", "work", "--", "--collection"]),
+            Ok(add(Some("work"), "--collection"))
+        );
+        // A lone dash is text too
+        assert_eq!(parse_args(&["add", "-", "x"]), Ok(add(None, "- x")));
+    }
+
+    #[test]
+    fn options_of_other_commands() {
+        assert_eq!(
+            parse_args(&["add", "--json", "Pay rent"]),
+            Err(ParseError::UnknownOption("--json".to_owned()))
+        );
+        assert_eq!(
+            parse_args(&["done", "-c", "work", "a"]),
+            Err(ParseError::UnknownOption("--collection".to_owned()))
+        );
+        assert_eq!(
+            parse_args(&["add", "--verbose", "Pay rent"]),
+            Err(ParseError::UnknownOption("--verbose".to_owned()))
+        );
+    }
+
+    #[test]
+    fn missing_values_and_arguments() {
+        for args in [&["add", "--collection"][..], &["list", "-c"]] {
+            assert_eq!(
+                parse_args(args),
+                Err(ParseError::MissingValue("--collection"))
+            );
+        }
+        assert_eq!(
+            parse_args(&["add"]),
+            Err(ParseError::MissingArgument("TEXT"))
+        );
+        assert_eq!(
+            parse_args(&["add", "-c", "work", "--"]),
+            Err(ParseError::MissingArgument("TEXT"))
+        );
+        assert_eq!(
+            parse_args(&["done"]),
+            Err(ParseError::MissingArgument("ID"))
+        );
+    }
+
+    #[test]
+    fn unexpected_arguments() {
+        assert_eq!(
+            parse_args(&["list", "everything"]),
+            Err(ParseError::UnexpectedArgument("everything".to_owned()))
+        );
+        assert_eq!(
+            parse_args(&["collections", "--json", "all"]),
+            Err(ParseError::UnexpectedArgument("all".to_owned()))
+        );
+        for args in [&["remove"][..], &["remove", "a"], &["remove", "--json"]] {
+            assert_eq!(
+                parse_args(args),
+                Err(ParseError::UnknownCommand("remove".to_owned()))
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn arguments_must_be_unicode() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let args = [OsString::from("add"), OsString::from_vec(vec![0xff, 0xfe])];
+        assert_eq!(parse(&args), Err(ParseError::NotUnicode));
+    }
+}
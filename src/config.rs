@@ -1 +1,3 @@
 pub const APP_ID: &str = "com.github.shahradelahi.Todo";
+/// Domain of everything the app logs through GLib.
+pub const LOG_DOMAIN: &str = "todo-gtk-rs";
@@ -0,0 +1,218 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// A calendar day, independent of any time zone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    year: i32,
+    month: u32,
+    day: u32,
+}
+
+impl Date {
+    pub fn new(year: i32, month: u32, day: u32) -> Option<Self> {
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(Self { year, month, day })
+    }
+
+    /// Parses the `YYYY-MM-DD` form used in the data file.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.splitn(3, '-');
+        let year = parts.next()?.parse().ok()?;
+        let month = parts.next()?.parse().ok()?;
+        let day = parts.next()?.parse().ok()?;
+        Self::new(year, month, day)
+    }
+
+    pub fn year(&self) -> i32 {
+        self.year
+    }
+
+    pub fn month(&self) -> u32 {
+        self.month
+    }
+
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+
+    pub fn weekday(&self) -> Weekday {
+        // 1970-01-01 was a Thursday
+        Weekday::ALL[(self.to_days() + 3).rem_euclid(7) as usize]
+    }
+
+    /// The date `days` days later, or earlier for negative `days`.
+    pub fn add_days(self, days: i64) -> Self {
+        self.checked_add_days(days).expect("Date out of range")
+    }
+
+    /// Like `add_days`, but `None` past the years an `i32` holds.
+    pub fn checked_add_days(self, days: i64) -> Option<Self> {
+        Self::from_days(self.to_days().checked_add(days)?)
+    }
+
+    /// How many days `self` is after `earlier`, negative if it is before.
+    pub fn days_since(self, earlier: Date) -> i64 {
+        self.to_days() - earlier.to_days()
+    }
+
+    /// Days since 1970-01-01, negative before it.
+    fn to_days(self) -> i64 {
+        // Howard Hinnant's `days_from_civil`, with years starting in March so
+        // the leap day comes last
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let month = i64::from(self.month);
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    fn from_days(days: i64) -> Option<Self> {
+        // Far enough for every `i32` year, near enough that nothing below overflows
+        if days.unsigned_abs() > 1 << 40 {
+            return None;
+        }
+
+        // The inverse, `civil_from_days`
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        } as u32;
+        let year = i32::try_from(year_of_era + era * 400 + i64::from(month <= 2)).ok()?;
+        Some(Self { year, month, day })
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    pub const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    /// Parses an English day name, either in full or abbreviated, in any case.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.to_lowercase();
+        let weekday = match text.as_str() {
+            "mon" | "monday" => Weekday::Monday,
+            "tue" | "tues" | "tuesday" => Weekday::Tuesday,
+            "wed" | "wednesday" => Weekday::Wednesday,
+            "thu" | "thur" | "thurs" | "thursday" => Weekday::Thursday,
+            "fri" | "friday" => Weekday::Friday,
+            "sat" | "saturday" => Weekday::Saturday,
+            "sun" | "sunday" => Weekday::Sunday,
+            _ => return None,
+        };
+        Some(weekday)
+    }
+
+    /// The three letter English abbreviation, like "mon".
+    pub fn short_name(self) -> &'static str {
+        match self {
+            Weekday::Monday => "mon",
+            Weekday::Tuesday => "tue",
+            Weekday::Wednesday => "wed",
+            Weekday::Thursday => "thu",
+            Weekday::Friday => "fri",
+            Weekday::Saturday => "sat",
+            Weekday::Sunday => "sun",
+        }
+    }
+
+    /// 0 for Monday up to 6 for Sunday.
+    pub fn number_from_monday(self) -> u32 {
+        self as u32
+    }
+
+    /// How many days it takes to get from `self` to `other`, 0 to 6.
+    pub fn days_until(self, other: Weekday) -> u32 {
+        (other.number_from_monday() + 7 - self.number_from_monday()) % 7
+    }
+}
+
+/// A wall-clock time of day with minute precision.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    hour: u32,
+    minute: u32,
+}
+
+impl Time {
+    pub fn new(hour: u32, minute: u32) -> Option<Self> {
+        if hour > 23 || minute > 59 {
+            return None;
+        }
+        Some(Self { hour, minute })
+    }
+
+    /// Parses the `HH:MM` form used in the data file.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (hour, minute) = text.split_once(':')?;
+        Self::new(hour.parse().ok()?, minute.parse().ok()?)
+    }
+
+    pub fn hour(&self) -> u32 {
+        self.hour
+    }
+
+    pub fn minute(&self) -> u32 {
+        self.minute
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+pub fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
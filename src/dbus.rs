@@ -0,0 +1,666 @@
+use std::collections::HashMap;
+
+use adw::prelude::*;
+use glib::clone;
+use gtk::{gio, glib};
+
+use crate::cli;
+use crate::config::LOG_DOMAIN;
+use crate::date::{Date, Time};
+use crate::quick_add;
+use crate::recurrence;
+use crate::tags;
+use crate::utils::split_date_time;
+use crate::widgets::{CollectionObject, Priority, TaskObject, Window};
+
+pub const INTERFACE_NAME: &str = "com.github.shahradelahi.Todo.Tasks";
+
+/// The documented introspection data, see the file for what every method does.
+const INTERFACE_PATH: &str =
+    "/com/github/shahradelahi/Todo/dbus/com.github.shahradelahi.Todo.Tasks.xml";
+
+/// Exports the tasks interface next to the ones `GApplication` exports itself.
+/// Calls go to the tasks of the first window, like commands from the command
+/// line do.
+pub fn export(app: &adw::Application) {
+    let (Some(connection), Some(object_path)) = (app.dbus_connection(), app.dbus_object_path())
+    else {
+        return;
+    };
+
+    let xml = gio::resources_lookup_data(INTERFACE_PATH, gio::ResourceLookupFlags::NONE)
+        .expect("Could not load the D-Bus interface");
+    let xml = std::str::from_utf8(&xml).expect("D-Bus interface is not UTF-8");
+
+    let window_collections = clone!(
+        #[weak]
+        app,
+        #[upgrade_or_else]
+        || Err(failed("the app is shutting down")),
+        move || {
+            let window = crate::windows(&app)
+                .next()
+                .ok_or_else(|| failed("there is no open window"))?;
+            Ok(window.collections())
+        }
+    );
+    if let Err(err) = register(&connection, &object_path, xml, window_collections) {
+        glib::g_warning!(LOG_DOMAIN, "Could not export the D-Bus interface: {err}");
+    }
+}
+
+/// Registers the interface described by `xml` at `object_path`, with calls
+/// going to the tasks of whatever `collections` gives at the time.
+fn register(
+    connection: &gio::DBusConnection,
+    object_path: &str,
+    xml: &str,
+    collections: impl Fn() -> Result<gio::ListStore, glib::Error> + 'static,
+) -> Result<gio::RegistrationId, glib::Error> {
+    let interface = gio::DBusNodeInfo::for_xml(xml)
+        .expect("D-Bus interface is not valid")
+        .lookup_interface(INTERFACE_NAME)
+        .expect("D-Bus interface is missing");
+
+    connection
+        .register_object(object_path, &interface)
+        .method_call(move |_, _, _, _, method, parameters, invocation| {
+            let result =
+                collections().and_then(|collections| call(&collections, method, &parameters));
+            invocation.return_result(result);
+        })
+        .build()
+}
+
+/// Emits `TaskChanged` whenever a task of `window` changes.
+pub fn watch_window(app: &adw::Application, window: &Window) {
+    window.connect_task_changed(clone!(
+        #[weak]
+        app,
+        move |task| {
+            if let (Some(connection), Some(object_path)) =
+                (app.dbus_connection(), app.dbus_object_path())
+            {
+                emit_task_changed(&connection, &object_path, task);
+            }
+        }
+    ));
+}
+
+fn emit_task_changed(connection: &gio::DBusConnection, object_path: &str, task: &TaskObject) {
+    let parameters = (task.id(),).to_variant();
+    if let Err(err) = connection.emit_signal(
+        None,
+        object_path,
+        INTERFACE_NAME,
+        "TaskChanged",
+        Some(&parameters),
+    ) {
+        glib::g_warning!(LOG_DOMAIN, "Could not emit TaskChanged: {err}");
+    }
+}
+
+/// Runs `method` on `collections`, whose parameters GDBus has already checked
+/// against the introspection data. Single return values get wrapped in a
+/// tuple later on.
+fn call(
+    collections: &gio::ListStore,
+    method: &str,
+    parameters: &glib::Variant,
+) -> Result<Option<glib::Variant>, glib::Error> {
+    match method {
+        "ListCollections" => {
+            let collections = collections
+                .iter::<CollectionObject>()
+                .filter_map(Result::ok)
+                .map(|collection| (collection.id(), collection.title()))
+                .collect::<Vec<_>>();
+            Ok(Some(collections.to_variant()))
+        }
+        "ListTasks" => {
+            let (collection,) = parameters.get::<(String,)>().expect("Checked by GDBus");
+            let listed = if collection.is_empty() {
+                collections
+                    .iter::<CollectionObject>()
+                    .filter_map(Result::ok)
+                    .collect()
+            } else {
+                vec![find_collection(collections, &collection)?]
+            };
+
+            let tasks = listed.iter().flat_map(|collection| {
+                collection
+                    .tasks_with_parents()
+                    .into_iter()
+                    .map(|(parent, task)| task_dict(&task, parent.as_ref(), collection))
+                    .collect::<Vec<_>>()
+            });
+            Ok(Some(glib::Variant::array_from_iter_with_type(
+                glib::VariantTy::VARDICT,
+                tasks,
+            )))
+        }
+        "GetTask" => {
+            let (id,) = parameters.get::<(String,)>().expect("Checked by GDBus");
+            let (collection, parent, task) = find_task(collections, &id)?;
+            Ok(Some(task_dict(&task, parent.as_ref(), &collection)))
+        }
+        "AddTask" => {
+            let (collection, text) = parameters
+                .get::<(String, String)>()
+                .expect("Checked by GDBus");
+            let collection = if collection.is_empty() {
+                cli::default_collection(collections)
+                    .ok_or_else(|| failed("there is no collection to add the task to"))?
+            } else {
+                find_collection(collections, &collection)?
+            };
+
+            let now = glib::DateTime::now_local().expect("Could not get the local time");
+            let (today, now) = split_date_time(&now);
+            let task = TaskObject::from_quick_add(
+                text.clone(),
+                quick_add::parse(&text, today, now),
+                false,
+            );
+            collection.tasks().append(&task);
+            Ok(Some(task.id().to_variant()))
+        }
+        "SetCompleted" => {
+            let (id, completed) = parameters
+                .get::<(String, bool)>()
+                .expect("Checked by GDBus");
+            let (_, _, task) = find_task(collections, &id)?;
+            task.set_completed(completed);
+            Ok(None)
+        }
+        "UpdateTask" => {
+            let (id, changes) = parameters
+                .get::<(String, HashMap<String, glib::Variant>)>()
+                .expect("Checked by GDBus");
+            let (_, _, task) = find_task(collections, &id)?;
+            update_task(&task, &changes)?;
+            Ok(None)
+        }
+        "DeleteTask" => {
+            let (id,) = parameters.get::<(String,)>().expect("Checked by GDBus");
+            let (collection, parent, task) = find_task(collections, &id)?;
+            let tasks = parent.map_or_else(|| collection.tasks(), |parent| parent.subtasks());
+            if let Some(position) = tasks.find(&task) {
+                tasks.remove(position);
+            }
+            Ok(None)
+        }
+        _ => Err(glib::Error::new(
+            gio::DBusError::UnknownMethod,
+            &format!("no method is called “{method}”"),
+        )),
+    }
+}
+
+/// Sets the properties in `changes`, checking all of them first so nothing
+/// changes when one is invalid.
+fn update_task(
+    task: &TaskObject,
+    changes: &HashMap<String, glib::Variant>,
+) -> Result<(), glib::Error> {
+    let mut properties = Vec::new();
+    let mut recurrence = None;
+    for (key, value) in changes {
+        let value = match key.as_str() {
+            "content" | "notes" => string(key, value)?.to_value(),
+            "completed" => value
+                .get::<bool>()
+                .ok_or_else(|| wrong_type(key, value, "b"))?
+                .to_value(),
+            "due-date" => {
+                let date = string(key, value)?;
+                if !date.is_empty() && Date::parse(&date).is_none() {
+                    return Err(invalid(format!("“{date}” is not a YYYY-MM-DD date")));
+                }
+                Some(date).filter(|date| !date.is_empty()).to_value()
+            }
+            "due-time" => {
+                let time = string(key, value)?;
+                if !time.is_empty() && Time::parse(&time).is_none() {
+                    return Err(invalid(format!("“{time}” is not a HH:MM time")));
+                }
+                Some(time).filter(|time| !time.is_empty()).to_value()
+            }
+            "priority" => {
+                let name = string(key, value)?;
+                Priority::parse(&name)
+                    .ok_or_else(|| invalid(format!("“{name}” is not a priority")))?
+                    .to_value()
+            }
+            "tags" => {
+                let names = value
+                    .get::<Vec<String>>()
+                    .ok_or_else(|| wrong_type(key, value, "as"))?;
+                names
+                    .iter()
+                    .map(|name| {
+                        tags::parse(&format!("#{name}"))
+                            .ok_or_else(|| invalid(format!("“{name}” is not a tag")))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+                    .to_value()
+            }
+            "recurrence" => {
+                recurrence = Some(string(key, value)?);
+                continue;
+            }
+            _ => return Err(invalid(format!("tasks have no “{key}”"))),
+        };
+        properties.push((key.as_str(), value));
+    }
+
+    // Parsed like in the due date dialog, taking left out days from the due date
+    let recurrence = match recurrence {
+        Some(text) if text.is_empty() => Some(None),
+        Some(text) => {
+            let due = match changes.get("due-date") {
+                Some(date) => date.str().and_then(Date::parse),
+                None => task.due().map(|(date, _)| date),
+            };
+            let now = glib::DateTime::now_local().expect("Could not get the local time");
+            let (today, _) = split_date_time(&now);
+            let rule = recurrence::parse(&text, due.unwrap_or(today))
+                .ok_or_else(|| invalid(format!("“{text}” is not a recurrence rule")))?;
+            Some(Some(rule))
+        }
+        None => None,
+    };
+
+    for (property, value) in properties {
+        task.set_property_from_value(property, &value);
+    }
+    if let Some(recurrence) = recurrence {
+        task.set_recurrence_rule(recurrence);
+    }
+    Ok(())
+}
+
+/// A task as an `a{sv}` dictionary, see the interface for the keys.
+fn task_dict(
+    task: &TaskObject,
+    parent: Option<&TaskObject>,
+    collection: &CollectionObject,
+) -> glib::Variant {
+    let dict = glib::VariantDict::new(None);
+    dict.insert_value("id", &task.id().to_variant());
+    dict.insert_value("collection", &collection.id().to_variant());
+    dict.insert_value(
+        "parent",
+        &parent.map(TaskObject::id).unwrap_or_default().to_variant(),
+    );
+    dict.insert_value("content", &task.content().to_variant());
+    dict.insert_value("notes", &task.notes().to_variant());
+    dict.insert_value("completed", &task.is_completed().to_variant());
+    dict.insert_value(
+        "due-date",
+        &task.due_date().unwrap_or_default().to_variant(),
+    );
+    dict.insert_value(
+        "due-time",
+        &task.due_time().unwrap_or_default().to_variant(),
+    );
+    dict.insert_value("priority", &task.priority().name().to_variant());
+    dict.insert_value("tags", &task.tags().to_variant());
+    dict.insert_value(
+        "recurrence",
+        &task.recurrence().unwrap_or_default().to_variant(),
+    );
+    dict.end()
+}
+
+fn find_collection(
+    collections: &gio::ListStore,
+    name: &str,
+) -> Result<CollectionObject, glib::Error> {
+    cli::find_collection(collections, name)
+        .ok_or_else(|| invalid(format!("no collection is called “{name}”")))
+}
+
+/// The task with `id` at any depth, with its collection and the task it is a
+/// subtask of.
+fn find_task(
+    collections: &gio::ListStore,
+    id: &str,
+) -> Result<(CollectionObject, Option<TaskObject>, TaskObject), glib::Error> {
+    collections
+        .iter::<CollectionObject>()
+        .filter_map(Result::ok)
+        .find_map(|collection| {
+            let (parent, task) = collection
+                .tasks_with_parents()
+                .into_iter()
+                .find(|(_, task)| task.id() == id)?;
+            Some((collection, parent, task))
+        })
+        .ok_or_else(|| invalid(format!("no task has the ID “{id}”")))
+}
+
+fn string(key: &str, value: &glib::Variant) -> Result<String, glib::Error> {
+    value
+        .get::<String>()
+        .ok_or_else(|| wrong_type(key, value, "s"))
+}
+
+fn wrong_type(key: &str, value: &glib::Variant, expected: &str) -> glib::Error {
+    invalid(format!(
+        "“{key}” has to be of type “{expected}”, not “{}”",
+        value.type_()
+    ))
+}
+
+fn invalid(message: String) -> glib::Error {
+    glib::Error::new(gio::DBusError::InvalidArgs, &message)
+}
+
+fn failed(message: &str) -> glib::Error {
+    glib::Error::new(gio::DBusError::Failed, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::persistence::Autosave;
+    use crate::widgets::{CollectionData, SavedFilterObject, TaskData};
+
+    const OBJECT_PATH: &str = "/com/github/shahradelahi/Todo";
+    const XML: &str = include_str!("../resources/dbus/com.github.shahradelahi.Todo.Tasks.xml");
+
+    /// `gio::TestDBus` sets environment variables, so one bus at a time.
+    static BUS: Mutex<()> = Mutex::new(());
+
+    type Dict = HashMap<String, glib::Variant>;
+
+    struct Fixture {
+        context: glib::MainContext,
+        service: gio::DBusConnection,
+        client: gio::DBusConnection,
+        collections: gio::ListStore,
+    }
+
+    impl Fixture {
+        fn call(
+            &self,
+            method: &str,
+            parameters: impl ToVariant,
+        ) -> Result<glib::Variant, glib::Error> {
+            let name = self.service.unique_name().expect("Connected to a bus");
+            self.context.block_on(self.client.call_future(
+                Some(&name),
+                OBJECT_PATH,
+                INTERFACE_NAME,
+                method,
+                Some(&parameters.to_variant()),
+                None,
+                gio::DBusCallFlags::NONE,
+                5000,
+            ))
+        }
+
+        fn get_task(&self, id: &str) -> Result<Dict, glib::Error> {
+            let (task,) = self.call("GetTask", (id,))?.get::<(Dict,)>().unwrap();
+            Ok(task)
+        }
+
+        fn list_tasks(&self, collection: &str) -> Vec<Dict> {
+            let reply = self.call("ListTasks", (collection,)).unwrap();
+            reply.get::<(Vec<Dict>,)>().unwrap().0
+        }
+
+        fn task(&self, id: &str) -> TaskObject {
+            self.collections
+                .iter::<CollectionObject>()
+                .filter_map(Result::ok)
+                .find_map(|collection| collection.find_task(id))
+                .unwrap()
+        }
+    }
+
+    fn task(id: &str, content: &str, subtasks: Vec<TaskData>) -> TaskData {
+        TaskData {
+            id: id.to_owned(),
+            content: content.to_owned(),
+            subtasks,
+            ..TaskData::default()
+        }
+    }
+
+    /// Runs `test` against the tasks of two collections, exported on a bus
+    /// of its own.
+    fn with_bus(test: impl FnOnce(&Fixture)) {
+        let _bus_lock = BUS.lock().unwrap_or_else(|err| err.into_inner());
+        let context = glib::MainContext::new();
+        context
+            .with_thread_default(|| {
+                let bus = gio::TestDBus::new(gio::TestDBusFlags::NONE);
+                bus.up();
+                let address = bus.bus_address().expect("The bus is up");
+                let connect = || {
+                    gio::DBusConnection::for_address_sync(
+                        &address,
+                        gio::DBusConnectionFlags::AUTHENTICATION_CLIENT
+                            | gio::DBusConnectionFlags::MESSAGE_BUS_CONNECTION,
+                        None,
+                        gio::Cancellable::NONE,
+                    )
+                    .unwrap()
+                };
+
+                let collections = gio::ListStore::new::<CollectionObject>();
+                collections.extend_from_slice(&[
+                    CollectionObject::from_collection_data(CollectionData {
+                        id: "home".to_owned(),
+                        title: "Home".to_owned(),
+                        tasks_data: vec![TaskData {
+                            due_date: Some("2026-11-01".to_owned()),
+                            priority: Priority::High,
+                            tags: vec!["bills".to_owned()],
+                            ..task(
+                                "rent",
+                                "Pay rent",
+                                vec![task("rent-1", "Find the IBAN", Vec::new())],
+                            )
+                        }],
+                    }),
+                    CollectionObject::from_collection_data(CollectionData {
+                        id: "work".to_owned(),
+                        title: "Work".to_owned(),
+                        tasks_data: vec![task("report", "Send report", Vec::new())],
+                    }),
+                ]);
+
+                let service = connect();
+                let store = collections.clone();
+                register(&service, OBJECT_PATH, XML, move || Ok(store.clone())).unwrap();
+
+                test(&Fixture {
+                    context: context.clone(),
+                    service,
+                    client: connect(),
+                    collections,
+                });
+                bus.down();
+            })
+            .unwrap();
+    }
+
+    fn string(dict: &Dict, key: &str) -> String {
+        dict[key].get::<String>().unwrap()
+    }
+
+    #[test]
+    fn lists_and_gets_tasks() {
+        with_bus(|fixture| {
+            let reply = fixture.call("ListCollections", ()).unwrap();
+            let (collections,) = reply.get::<(Vec<(String, String)>,)>().unwrap();
+            let expected = [("home", "Home"), ("work", "Work")];
+            assert_eq!(
+                collections,
+                expected.map(|(id, title)| (id.to_owned(), title.to_owned()))
+            );
+
+            let ids = |tasks: Vec<Dict>| {
+                tasks
+                    .iter()
+                    .map(|task| (string(task, "id"), string(task, "parent")))
+                    .collect::<Vec<_>>()
+            };
+            let expected = [("rent", ""), ("rent-1", "rent"), ("report", "")]
+                .map(|(id, parent)| (id.to_owned(), parent.to_owned()));
+            assert_eq!(ids(fixture.list_tasks("")), expected);
+            assert_eq!(ids(fixture.list_tasks("work")), expected[2..]);
+            assert!(fixture.call("ListTasks", ("garden",)).is_err());
+
+            let rent = fixture.get_task("rent").unwrap();
+            assert_eq!(string(&rent, "collection"), "home");
+            assert_eq!(string(&rent, "content"), "Pay rent");
+            assert_eq!(string(&rent, "due-date"), "2026-11-01");
+            assert_eq!(string(&rent, "due-time"), "");
+            assert_eq!(string(&rent, "priority"), "high");
+            assert_eq!(rent["tags"].get::<Vec<String>>().unwrap(), ["bills"]);
+            assert_eq!(rent["completed"].get::<bool>(), Some(false));
+
+            let subtask = fixture.get_task("rent-1").unwrap();
+            assert_eq!(string(&subtask, "content"), "Find the IBAN");
+            assert_eq!(string(&subtask, "parent"), "rent");
+
+            let err = fixture.get_task("missing").unwrap_err();
+            assert!(err.matches(gio::DBusError::InvalidArgs), "{err}");
+        });
+    }
+
+    #[test]
+    fn adds_tasks() {
+        with_bus(|fixture| {
+            let reply = fixture
+                .call("AddTask", ("work", "Water plants tomorrow #Home !!"))
+                .unwrap();
+            let (id,) = reply.get::<(String,)>().unwrap();
+
+            let task = fixture.get_task(&id).unwrap();
+            assert_eq!(string(&task, "collection"), "work");
+            assert_eq!(string(&task, "content"), "Water plants");
+            assert_eq!(string(&task, "priority"), "medium");
+            assert_eq!(task["tags"].get::<Vec<String>>().unwrap(), ["home"]);
+            assert!(Date::parse(&string(&task, "due-date")).is_some());
+            assert_eq!(fixture.list_tasks("work").len(), 2);
+        });
+    }
+
+    #[test]
+    fn rejects_invalid_changes() {
+        with_bus(|fixture| {
+            let invalid_changes = [
+                ("colour", "red".to_variant()),
+                ("priority", "highest".to_variant()),
+                ("due-date", "soon".to_variant()),
+                ("due-time", 9u32.to_variant()),
+                ("recurrence", "now and then".to_variant()),
+            ];
+            for (key, value) in invalid_changes {
+                let changes = Dict::from([
+                    ("content".to_owned(), "Pay the rent".to_variant()),
+                    (key.to_owned(), value),
+                ]);
+                let err = fixture.call("UpdateTask", ("rent", changes)).unwrap_err();
+                assert!(err.matches(gio::DBusError::InvalidArgs), "{key}: {err}");
+
+                let task = fixture.task("rent");
+                assert_eq!(task.content(), "Pay rent", "{key}");
+                assert_eq!(task.priority(), Priority::High, "{key}");
+                assert_eq!(task.due_date().as_deref(), Some("2026-11-01"), "{key}");
+                assert_eq!(task.recurrence(), None, "{key}");
+            }
+
+            let changes = Dict::from([
+                ("content".to_owned(), "Pay the rent".to_variant()),
+                ("due-time".to_owned(), "09:30".to_variant()),
+                ("recurrence".to_owned(), "monthly".to_variant()),
+            ]);
+            fixture.call("UpdateTask", ("rent-1", changes)).unwrap();
+            let subtask = fixture.task("rent-1");
+            assert_eq!(subtask.content(), "Pay the rent");
+            assert_eq!(subtask.due_time().as_deref(), Some("09:30"));
+            assert!(subtask.recurrence_rule().is_some());
+        });
+    }
+
+    #[test]
+    fn deletes_subtasks() {
+        with_bus(|fixture| {
+            fixture.call("DeleteTask", ("rent-1",)).unwrap();
+            assert!(fixture.get_task("rent-1").is_err());
+            assert_eq!(fixture.task("rent").subtasks().n_items(), 0);
+            assert_eq!(fixture.list_tasks("").len(), 2);
+
+            fixture.call("DeleteTask", ("report",)).unwrap();
+            assert!(fixture.list_tasks("work").is_empty());
+            assert!(fixture.call("DeleteTask", ("report",)).is_err());
+        });
+    }
+
+    #[test]
+    fn signals_changed_tasks() {
+        with_bus(|fixture| {
+            let autosave = Autosave::new(
+                &fixture.collections,
+                &gio::ListStore::new::<SavedFilterObject>(),
+            );
+            // Nothing here should end up in the data file
+            autosave.set_enabled(false);
+            let service = fixture.service.clone();
+            autosave.connect_task_changed(move |task| {
+                emit_task_changed(&service, OBJECT_PATH, task);
+            });
+
+            let changed = Rc::new(RefCell::new(Vec::new()));
+            let subscription = fixture.client.signal_subscribe(
+                fixture.service.unique_name().as_deref(),
+                Some(INTERFACE_NAME),
+                Some("TaskChanged"),
+                Some(OBJECT_PATH),
+                None,
+                gio::DBusSignalFlags::NONE,
+                clone!(
+                    #[strong]
+                    changed,
+                    move |_, _, _, _, _, parameters| {
+                        let (id,) = parameters.get::<(String,)>().unwrap();
+                        changed.borrow_mut().push(id);
+                    }
+                ),
+            );
+
+            // A subtask changing is a change to its top-level task
+            fixture.call("SetCompleted", ("rent-1", true)).unwrap();
+            fixture
+                .call(
+                    "UpdateTask",
+                    (
+                        "report",
+                        Dict::from([("content".to_owned(), "Send the report".to_variant())]),
+                    ),
+                )
+                .unwrap();
+
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while changed.borrow().len() < 2 && Instant::now() < deadline {
+                if !fixture.context.iteration(false) {
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+            }
+            assert_eq!(*changed.borrow(), ["rent", "report"]);
+            fixture.client.signal_unsubscribe(subscription);
+        });
+    }
+}
@@ -0,0 +1,943 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+
+use crate::date::{Date, Time, Weekday};
+use crate::recurrence::{Recurrence, MAX_INTERVAL};
+use crate::tags;
+use crate::widgets::{Priority, TaskData};
+
+/// iCalendar has no rule for "N days after completion", so those are kept in
+/// this property next to the tasks, for this app to read back.
+const RECURRENCE_PROPERTY: &str = "X-TODO-RECURRENCE";
+
+/// The tasks of an `.ics` file.
+#[derive(Clone, Debug, Default)]
+pub struct Calendar {
+    /// `X-WR-CALNAME`, which most clients write
+    pub name: Option<String>,
+    /// With their `UID`s as IDs, subtasks nested under the task their
+    /// `RELATED-TO` points at
+    pub tasks: Vec<TaskData>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    /// There is no `BEGIN:VCALENDAR`
+    NotICalendar,
+    /// A `BEGIN` without its `END`, or an `END` without its `BEGIN`
+    Unbalanced(String),
+    /// A line that is not `NAME[;PARAMS]:VALUE`
+    InvalidLine(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::NotICalendar => write!(f, "not an iCalendar file"),
+            ParseError::Unbalanced(component) => write!(f, "“{component}” is not closed properly"),
+            ParseError::InvalidLine(line) => write!(f, "“{line}” is not a valid line"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Writes `tasks` as a calendar of VTODOs called `name`, subtasks pointing at
+/// their task with `RELATED-TO`. Due dates are floating, in whatever time
+/// zone the reader is in, like they are here. `stamp` is the Unix time of
+/// the export.
+pub fn export(name: &str, tasks: &[TaskData], stamp: i64) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        "PRODID:-//shahradelahi//Todo//EN".to_owned(),
+        format!("X-WR-CALNAME:{}", escape(name)),
+    ];
+    for task in tasks {
+        export_task(&mut lines, task, None, stamp);
+    }
+    lines.push("END:VCALENDAR".to_owned());
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+fn export_task(lines: &mut Vec<String>, task: &TaskData, parent: Option<&str>, stamp: i64) {
+    lines.push("BEGIN:VTODO".to_owned());
+    lines.push(format!("UID:{}", task.id));
+    lines.extend(format_utc(stamp).map(|stamp| format!("DTSTAMP:{stamp}")));
+    if task.created_at != 0 {
+        lines.extend(format_utc(task.created_at).map(|created| format!("CREATED:{created}")));
+    }
+    if task.modified_at != 0 {
+        lines.extend(
+            format_utc(task.modified_at).map(|modified| format!("LAST-MODIFIED:{modified}")),
+        );
+    }
+    lines.push(format!("SUMMARY:{}", escape(&task.content)));
+    if !task.notes.is_empty() {
+        lines.push(format!("DESCRIPTION:{}", escape(&task.notes)));
+    }
+    let status = if task.completed {
+        "COMPLETED"
+    } else {
+        "NEEDS-ACTION"
+    };
+    lines.push(format!("STATUS:{status}"));
+
+    if let Some(date) = task.due_date.as_deref().and_then(Date::parse) {
+        match task.due_time.as_deref().and_then(Time::parse) {
+            Some(time) => lines.push(format!("DUE:{}T{}", format_date(date), format_time(time))),
+            None => lines.push(format!("DUE;VALUE=DATE:{}", format_date(date))),
+        }
+    }
+    if let Some(priority) = export_priority(task.priority) {
+        lines.push(format!("PRIORITY:{priority}"));
+    }
+    if !task.tags.is_empty() {
+        let tags = task.tags.iter().map(|tag| escape(tag)).collect::<Vec<_>>();
+        lines.push(format!("CATEGORIES:{}", tags.join(",")));
+    }
+    match &task.recurrence {
+        Some(recurrence @ Recurrence::AfterCompletion { .. }) => {
+            lines.push(format!(
+                "{RECURRENCE_PROPERTY}:{}",
+                escape(&recurrence.to_string())
+            ));
+        }
+        Some(recurrence) => lines.push(format!("RRULE:{}", export_rrule(recurrence))),
+        None => {}
+    }
+    if let Some(parent) = parent {
+        lines.push(format!("RELATED-TO:{parent}"));
+    }
+    lines.push("END:VTODO".to_owned());
+
+    for subtask in &task.subtasks {
+        export_task(lines, subtask, Some(&task.id), stamp);
+    }
+}
+
+/// 1 is the most urgent and 9 the least, 0 and leaving it out mean none.
+fn export_priority(priority: Priority) -> Option<u32> {
+    match priority {
+        Priority::None => None,
+        Priority::Low => Some(9),
+        Priority::Medium => Some(5),
+        Priority::High => Some(3),
+        Priority::Urgent => Some(1),
+    }
+}
+
+fn import_priority(value: &str) -> Priority {
+    match value.trim().parse::<u32>() {
+        Ok(1) => Priority::Urgent,
+        Ok(2..=4) => Priority::High,
+        Ok(5) => Priority::Medium,
+        Ok(6..=9) => Priority::Low,
+        _ => Priority::None,
+    }
+}
+
+fn export_rrule(recurrence: &Recurrence) -> String {
+    let mut rule = String::new();
+    match recurrence {
+        Recurrence::Daily { interval } => write_frequency(&mut rule, "DAILY", *interval),
+        Recurrence::Weekly { interval, weekdays } => {
+            write_frequency(&mut rule, "WEEKLY", *interval);
+            if !weekdays.is_empty() {
+                let days = weekdays
+                    .iter()
+                    .map(|&weekday| weekday_code(weekday))
+                    .collect::<Vec<_>>();
+                write!(rule, ";BYDAY={}", days.join(",")).unwrap();
+            }
+        }
+        Recurrence::Monthly { interval, day } => {
+            write_frequency(&mut rule, "MONTHLY", *interval);
+            write!(rule, ";BYMONTHDAY={day}").unwrap();
+        }
+        Recurrence::Yearly {
+            interval,
+            month,
+            day,
+        } => {
+            write_frequency(&mut rule, "YEARLY", *interval);
+            write!(rule, ";BYMONTH={month};BYMONTHDAY={day}").unwrap();
+        }
+        // Written to `RECURRENCE_PROPERTY` instead
+        Recurrence::AfterCompletion { days } => write_frequency(&mut rule, "DAILY", *days),
+    }
+    rule
+}
+
+fn write_frequency(rule: &mut String, frequency: &str, interval: u32) {
+    write!(rule, "FREQ={frequency}").unwrap();
+    if interval > 1 {
+        write!(rule, ";INTERVAL={interval}").unwrap();
+    }
+}
+
+/// Reads the rules that map onto a `Recurrence`, taking anchors that are left
+/// out from `due`. `COUNT` and `UNTIL` are dropped, tasks here recur forever.
+/// Intervals beyond what the app itself takes leave the rule out.
+fn import_rrule(value: &str, due: Option<Date>) -> Option<Recurrence> {
+    let parts = value
+        .split(';')
+        .map(|part| part.split_once('='))
+        .collect::<Option<HashMap<_, _>>>()?;
+    let interval = match parts.get("INTERVAL") {
+        Some(interval) => interval
+            .parse()
+            .ok()
+            .filter(|n: &u32| (1..=MAX_INTERVAL).contains(n))?,
+        None => 1,
+    };
+    let single = |key| -> Option<Option<u32>> {
+        match parts.get(key) {
+            Some(value) => value.parse().ok().map(Some),
+            None => Some(None),
+        }
+    };
+    let month_day = single("BYMONTHDAY")?;
+    let month = single("BYMONTH")?;
+    if month_day.is_some_and(|day| !(1..=31).contains(&day))
+        || month.is_some_and(|month| !(1..=12).contains(&month))
+    {
+        return None;
+    }
+
+    let known = [
+        "FREQ",
+        "INTERVAL",
+        "COUNT",
+        "UNTIL",
+        "WKST",
+        "BYDAY",
+        "BYMONTHDAY",
+        "BYMONTH",
+    ];
+    if parts.keys().any(|key| !known.contains(key)) {
+        return None;
+    }
+    let allowed = |keys: &[&str]| {
+        ["BYDAY", "BYMONTHDAY", "BYMONTH"]
+            .iter()
+            .all(|key| keys.contains(key) || !parts.contains_key(key))
+    };
+
+    let anchor = due.unwrap_or_else(epoch);
+    let recurrence = match *parts.get("FREQ")? {
+        "DAILY" if allowed(&[]) => Recurrence::Daily { interval },
+        "WEEKLY" if allowed(&["BYDAY"]) => {
+            let weekdays = match parts.get("BYDAY") {
+                Some(days) => days
+                    .split(',')
+                    .map(|code| {
+                        Weekday::ALL
+                            .into_iter()
+                            .find(|&day| weekday_code(day) == code)
+                    })
+                    .collect::<Option<Vec<_>>>()?,
+                None => Vec::new(),
+            };
+            Recurrence::Weekly { interval, weekdays }
+        }
+        "MONTHLY" if allowed(&["BYMONTHDAY"]) => Recurrence::Monthly {
+            interval,
+            day: month_day.unwrap_or(anchor.day()),
+        },
+        "YEARLY" if allowed(&["BYMONTH", "BYMONTHDAY"]) => Recurrence::Yearly {
+            interval,
+            month: month.unwrap_or(anchor.month()),
+            day: month_day.unwrap_or(anchor.day()),
+        },
+        _ => return None,
+    };
+    Some(recurrence)
+}
+
+/// Like "MO", as in `BYDAY`.
+fn weekday_code(weekday: Weekday) -> String {
+    weekday.short_name()[..2].to_uppercase()
+}
+
+/// Reads the VTODOs of an `.ics` file, leaving out what has no counterpart
+/// here and values that don't parse. UTC times go through `from_utc` to end
+/// up in local time, times in other zones are taken as they are.
+pub fn parse(
+    text: &str,
+    from_utc: impl Fn(Date, Time) -> (Date, Time),
+) -> Result<Calendar, ParseError> {
+    let mut name = None;
+    let mut todos = Vec::new();
+    let mut components = Vec::<String>::new();
+    let mut properties = Vec::new();
+    let mut seen_calendar = false;
+
+    for line in unfold(text) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some(property) = Property::parse(&line) else {
+            return Err(if seen_calendar {
+                ParseError::InvalidLine(line)
+            } else {
+                ParseError::NotICalendar
+            });
+        };
+        match property.name.as_str() {
+            "BEGIN" => {
+                let component = property.value.to_uppercase();
+                seen_calendar |= component == "VCALENDAR";
+                if component == "VTODO" {
+                    properties.clear();
+                }
+                components.push(component);
+            }
+            "END" => {
+                let component = property.value.to_uppercase();
+                if components.pop().as_ref() != Some(&component) {
+                    return Err(ParseError::Unbalanced(component));
+                }
+                if component == "VTODO" {
+                    todos.push(import_todo(&properties, &from_utc));
+                }
+            }
+            _ => match components.last().map(String::as_str) {
+                Some("VTODO") => properties.push(property),
+                Some("VCALENDAR") if property.name == "X-WR-CALNAME" => {
+                    name = Some(unescape(&property.value));
+                }
+                _ => {}
+            },
+        }
+    }
+
+    if let Some(component) = components.pop() {
+        return Err(ParseError::Unbalanced(component));
+    }
+    if !seen_calendar {
+        return Err(ParseError::NotICalendar);
+    }
+    Ok(Calendar {
+        name: name.filter(|name| !name.trim().is_empty()),
+        tasks: nest(todos),
+    })
+}
+
+/// A task, and the UID of the task it is a subtask of.
+type Todo = (TaskData, Option<String>);
+
+fn import_todo(properties: &[Property], from_utc: &impl Fn(Date, Time) -> (Date, Time)) -> Todo {
+    let mut task = TaskData::default();
+    let mut parent = None;
+    let mut rrule = None;
+    let mut recurrence = None;
+
+    for property in properties {
+        let value = property.value.as_str();
+        match property.name.as_str() {
+            "UID" => task.id = value.to_owned(),
+            "SUMMARY" => task.content = unescape(value),
+            "DESCRIPTION" => task.notes = unescape(value),
+            "STATUS" => task.completed = value.eq_ignore_ascii_case("COMPLETED"),
+            "DUE" => {
+                if let Some((date, time)) = parse_date_time(value, from_utc) {
+                    task.due_date = Some(date.to_string());
+                    task.due_time = time.map(|time| time.to_string());
+                }
+            }
+            "PRIORITY" => task.priority = import_priority(value),
+            "CATEGORIES" => {
+                let names = split_list(value);
+                let new_tags = names
+                    .iter()
+                    .filter_map(|name| tags::parse(&format!("#{}", name.trim().replace(' ', "-"))));
+                for tag in new_tags {
+                    if !task.tags.contains(&tag) {
+                        task.tags.push(tag);
+                    }
+                }
+            }
+            "RRULE" => rrule = Some(value),
+            RECURRENCE_PROPERTY => recurrence = unescape(value).parse().ok(),
+            "CREATED" => task.created_at = parse_utc(value).unwrap_or_default(),
+            "LAST-MODIFIED" => task.modified_at = parse_utc(value).unwrap_or_default(),
+            // Only parents, the other relations don't make subtasks
+            "RELATED-TO" => {
+                let relation = property.parameter("RELTYPE");
+                if relation.is_none_or(|relation| relation.eq_ignore_ascii_case("PARENT")) {
+                    parent = Some(value.to_owned());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let due = task.due_date.as_deref().and_then(Date::parse);
+    task.recurrence = recurrence.or_else(|| import_rrule(rrule?, due));
+    (task, parent)
+}
+
+/// Puts every task with a parent in the file under it. Tasks whose parent
+/// is missing, or that are their own ancestors, stay at the top.
+fn nest(todos: Vec<Todo>) -> Vec<TaskData> {
+    let uids = todos
+        .iter()
+        .map(|(task, _)| task.id.as_str())
+        .collect::<HashSet<_>>();
+    let mut children = HashMap::<String, Vec<usize>>::new();
+    let mut roots = Vec::new();
+    for (index, (task, parent)) in todos.iter().enumerate() {
+        match parent {
+            Some(parent) if uids.contains(parent.as_str()) && *parent != task.id => {
+                children.entry(parent.clone()).or_default().push(index);
+            }
+            _ => roots.push(index),
+        }
+    }
+
+    let mut todos = todos
+        .into_iter()
+        .map(|(task, _)| Some(task))
+        .collect::<Vec<_>>();
+    let mut tasks = Vec::new();
+    for index in roots.into_iter().chain(0..todos.len()) {
+        if let Some(task) = take_with_subtasks(&mut todos, &children, index) {
+            tasks.push(task);
+        }
+    }
+    tasks
+}
+
+/// Takes the task at `index` out of `todos`, with the subtasks under it that
+/// are not taken yet.
+fn take_with_subtasks(
+    todos: &mut [Option<TaskData>],
+    children: &HashMap<String, Vec<usize>>,
+    index: usize,
+) -> Option<TaskData> {
+    let mut task = todos[index].take()?;
+    for &child in children.get(&task.id).into_iter().flatten() {
+        if let Some(subtask) = take_with_subtasks(todos, children, child) {
+            task.subtasks.push(subtask);
+        }
+    }
+    Some(task)
+}
+
+/// A content line, with the name and parameter names uppercased.
+struct Property {
+    name: String,
+    parameters: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn parse(line: &str) -> Option<Self> {
+        // The value starts at the first colon outside of a quoted parameter
+        let mut quoted = false;
+        let colon = line.char_indices().find_map(|(index, c)| match c {
+            '"' => {
+                quoted = !quoted;
+                None
+            }
+            ':' if !quoted => Some(index),
+            _ => None,
+        })?;
+        let (head, value) = (&line[..colon], &line[colon + 1..]);
+
+        let mut parts = head.split(';');
+        let name = parts.next()?.trim().to_uppercase();
+        if name.is_empty() {
+            return None;
+        }
+        let parameters = parts
+            .filter_map(|parameter| parameter.split_once('='))
+            .map(|(key, value)| {
+                (
+                    key.trim().to_uppercase(),
+                    value.trim_matches('"').to_owned(),
+                )
+            })
+            .collect();
+
+        Some(Self {
+            name,
+            parameters,
+            value: value.to_owned(),
+        })
+    }
+
+    fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Joins lines continued with a leading space or tab back together.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines = Vec::<String>::new();
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+    lines
+}
+
+/// Breaks `line` up so no part is longer than 75 bytes, and ends it with CRLF.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(next) => unescaped.push(next),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Splits a list value like `CATEGORIES` at the commas that are not escaped,
+/// unescaping each item.
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = vec![String::new()];
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                items.last_mut().unwrap().push(c);
+                if let Some(next) = chars.next() {
+                    items.last_mut().unwrap().push(next);
+                }
+            }
+            ',' => items.push(String::new()),
+            _ => items.last_mut().unwrap().push(c),
+        }
+    }
+    items
+        .iter()
+        .map(|item| unescape(item))
+        .filter(|item| !item.trim().is_empty())
+        .collect()
+}
+
+fn format_date(date: Date) -> String {
+    format!("{:04}{:02}{:02}", date.year(), date.month(), date.day())
+}
+
+fn format_time(time: Time) -> String {
+    format!("{:02}{:02}00", time.hour(), time.minute())
+}
+
+/// Unix time as a UTC date-time like "20261018T093000Z", `None` if it is
+/// too far off to be a date.
+fn format_utc(unix: i64) -> Option<String> {
+    let (date, seconds) = (
+        epoch().checked_add_days(unix.div_euclid(86_400))?,
+        unix.rem_euclid(86_400),
+    );
+    let formatted = format!(
+        "{}T{:02}{:02}{:02}Z",
+        format_date(date),
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    Some(formatted)
+}
+
+fn parse_utc(value: &str) -> Option<i64> {
+    let (date, time) = value.trim().strip_suffix('Z')?.split_once('T')?;
+    let date = parse_date(date)?;
+    let (hour, minute, second) = parse_hms(time)?;
+    Some(date.days_since(epoch()) * 86_400 + i64::from(hour * 3600 + minute * 60 + second))
+}
+
+/// A `DATE` like "20261101" or a `DATE-TIME` like "20261101T090000", "Z"
+/// at the end for UTC. Seconds are dropped.
+fn parse_date_time(
+    value: &str,
+    from_utc: &impl Fn(Date, Time) -> (Date, Time),
+) -> Option<(Date, Option<Time>)> {
+    let value = value.trim();
+    let Some((date, time)) = value.split_once('T') else {
+        return Some((parse_date(value)?, None));
+    };
+
+    let date = parse_date(date)?;
+    let (time, utc) = match time.strip_suffix('Z') {
+        Some(time) => (time, true),
+        None => (time, false),
+    };
+    let (hour, minute, _) = parse_hms(time)?;
+    let time = Time::new(hour, minute)?;
+    let (date, time) = if utc {
+        from_utc(date, time)
+    } else {
+        (date, time)
+    };
+    Some((date, Some(time)))
+}
+
+fn parse_date(text: &str) -> Option<Date> {
+    if text.len() != 8 || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Date::new(
+        text[..4].parse().ok()?,
+        text[4..6].parse().ok()?,
+        text[6..].parse().ok()?,
+    )
+}
+
+fn parse_hms(text: &str) -> Option<(u32, u32, u32)> {
+    if text.len() != 6 || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((
+        text[..2].parse().ok()?,
+        text[2..4].parse().ok()?,
+        text[4..].parse().ok()?,
+    ))
+}
+
+fn epoch() -> Date {
+    Date::new(1970, 1, 1).expect("Valid date")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2026-10-18 09:30:00 UTC.
+    const STAMP: i64 = 1_792_315_800;
+
+    /// Times are taken as they are, as if local time was UTC.
+    fn parse_utc_as_local(text: &str) -> Result<Calendar, ParseError> {
+        parse(text, |date, time| (date, time))
+    }
+
+    fn round_trip(tasks: &[TaskData]) -> Calendar {
+        let text = export("Chores", tasks, STAMP);
+        parse_utc_as_local(&text).unwrap_or_else(|err| panic!("{err}\n{text}"))
+    }
+
+    /// Tasks compared by everything they store.
+    fn json(tasks: &[TaskData]) -> serde_json::Value {
+        serde_json::to_value(tasks).unwrap()
+    }
+
+    fn task(id: &str, content: &str) -> TaskData {
+        TaskData {
+            id: id.to_owned(),
+            content: content.to_owned(),
+            ..TaskData::default()
+        }
+    }
+
+    fn due(mut task: TaskData, date: &str, time: Option<&str>) -> TaskData {
+        task.due_date = Some(date.to_owned());
+        task.due_time = time.map(str::to_owned);
+        task
+    }
+
+    fn recurring(mut task: TaskData, recurrence: Recurrence) -> TaskData {
+        task.recurrence = Some(recurrence);
+        task
+    }
+
+    #[test]
+    fn round_trips_every_field() {
+        let tasks = vec![TaskData {
+            notes: "- Water\n- Power".to_owned(),
+            completed: true,
+            priority: Priority::High,
+            tags: vec!["home".to_owned(), "q3-plan".to_owned()],
+            created_at: 1_790_000_000,
+            modified_at: 1_792_000_000,
+            ..due(task("a", "Pay the bills"), "2026-11-01", Some("09:30"))
+        }];
+
+        let calendar = round_trip(&tasks);
+        assert_eq!(calendar.name.as_deref(), Some("Chores"));
+        assert_eq!(json(&calendar.tasks), json(&tasks));
+    }
+
+    #[test]
+    fn round_trips_recurrence() {
+        let rules = [
+            Recurrence::Daily { interval: 1 },
+            Recurrence::Daily { interval: 3 },
+            Recurrence::Weekly {
+                interval: 1,
+                weekdays: Vec::new(),
+            },
+            Recurrence::Weekly {
+                interval: 2,
+                weekdays: vec![Weekday::Monday, Weekday::Friday],
+            },
+            Recurrence::Monthly {
+                interval: 1,
+                day: 31,
+            },
+            Recurrence::Yearly {
+                interval: 4,
+                month: 2,
+                day: 29,
+            },
+            Recurrence::AfterCompletion { days: 10 },
+        ];
+        let tasks = rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| {
+                let task = due(task(&index.to_string(), "Recur"), "2026-10-20", None);
+                recurring(task, rule.clone())
+            })
+            .collect::<Vec<_>>();
+
+        let text = export("Chores", &tasks, STAMP);
+        assert!(text.contains("RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR\r\n"));
+        assert!(text.contains("RRULE:FREQ=YEARLY;INTERVAL=4;BYMONTH=2;BYMONTHDAY=29\r\n"));
+        assert!(text.contains("X-TODO-RECURRENCE:every 10 days after completion\r\n"));
+        assert_eq!(json(&round_trip(&tasks).tasks), json(&tasks));
+    }
+
+    #[test]
+    fn reads_rrules_of_other_clients() {
+        let due = Date::new(2026, 1, 31);
+        let weekly = Recurrence::Weekly {
+            interval: 1,
+            weekdays: vec![Weekday::Monday, Weekday::Wednesday],
+        };
+        assert_eq!(
+            import_rrule("FREQ=WEEKLY;BYDAY=MO,WE;COUNT=5;WKST=SU", due),
+            Some(weekly)
+        );
+        assert_eq!(
+            import_rrule("FREQ=MONTHLY", due),
+            Some(Recurrence::Monthly {
+                interval: 1,
+                day: 31
+            })
+        );
+        assert_eq!(
+            import_rrule("FREQ=YEARLY;INTERVAL=2", due),
+            Some(Recurrence::Yearly {
+                interval: 2,
+                month: 1,
+                day: 31
+            })
+        );
+        for rule in [
+            "FREQ=HOURLY",
+            "FREQ=MONTHLY;BYDAY=1MO",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;INTERVAL=5000",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "INTERVAL=2",
+        ] {
+            assert_eq!(import_rrule(rule, due), None, "{rule}");
+        }
+    }
+
+    #[test]
+    fn due_with_and_without_time() {
+        let tasks = vec![
+            due(task("a", "All day"), "2026-11-01", None),
+            due(task("b", "At nine"), "2026-11-01", Some("09:00")),
+        ];
+
+        let text = export("Chores", &tasks, STAMP);
+        assert!(text.contains("DUE;VALUE=DATE:20261101\r\n"));
+        assert!(text.contains("DUE:20261101T090000\r\n"));
+        assert_eq!(json(&round_trip(&tasks).tasks), json(&tasks));
+    }
+
+    #[test]
+    fn utc_due_times_become_local() {
+        let text = "BEGIN:VCALENDAR\r\n\
+                    BEGIN:VTODO\r\n\
+                    UID:a\r\n\
+                    DUE:20261231T233000Z\r\n\
+                    END:VTODO\r\n\
+                    END:VCALENDAR\r\n";
+        // Two hours ahead of UTC
+        let calendar = parse(text, |date, time| {
+            let minutes = time.hour() * 60 + time.minute() + 120;
+            let time = Time::new(minutes / 60 % 24, minutes % 60).unwrap();
+            (date.add_days(i64::from(minutes / (24 * 60))), time)
+        })
+        .unwrap();
+
+        let task = &calendar.tasks[0];
+        assert_eq!(task.due_date.as_deref(), Some("2027-01-01"));
+        assert_eq!(task.due_time.as_deref(), Some("01:30"));
+    }
+
+    #[test]
+    fn subtasks_round_trip_through_related_to() {
+        let tasks = vec![
+            TaskData {
+                subtasks: vec![
+                    TaskData {
+                        subtasks: vec![task("a1i", "Compare prices")],
+                        ..task("a1", "Pick a flight")
+                    },
+                    task("a2", "Book a hotel"),
+                ],
+                ..task("a", "Plan the trip")
+            },
+            task("b", "Renew passport"),
+        ];
+
+        let text = export("Chores", &tasks, STAMP);
+        assert!(text.contains("UID:a1i\r\n"));
+        assert!(text.contains("RELATED-TO:a1\r\n"));
+        assert_eq!(json(&round_trip(&tasks).tasks), json(&tasks));
+    }
+
+    #[test]
+    fn nests_subtasks_wherever_they_are() {
+        let text = "BEGIN:VCALENDAR\r\n\
+                    BEGIN:VTODO\r\nUID:child\r\nRELATED-TO:parent\r\nEND:VTODO\r\n\
+                    BEGIN:VTODO\r\nUID:sibling\r\nRELATED-TO;RELTYPE=SIBLING:parent\r\nEND:VTODO\r\n\
+                    BEGIN:VTODO\r\nUID:orphan\r\nRELATED-TO:missing\r\nEND:VTODO\r\n\
+                    BEGIN:VTODO\r\nUID:parent\r\nEND:VTODO\r\n\
+                    BEGIN:VTODO\r\nUID:loop\r\nRELATED-TO:loop\r\nEND:VTODO\r\n\
+                    END:VCALENDAR\r\n";
+
+        let tasks = parse_utc_as_local(text).unwrap().tasks;
+        let ids = tasks
+            .iter()
+            .map(|task| task.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["sibling", "orphan", "parent", "loop"]);
+        assert_eq!(tasks[2].subtasks[0].id, "child");
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        let content =
+            "Ask about the lease, the deposit and the keys 🔑 before the end of the month";
+        let notes = "Ünïcödé ".repeat(30);
+        let tasks = vec![TaskData {
+            notes: notes.trim_end().to_owned(),
+            ..task("a", content)
+        }];
+
+        let text = export("Chores", &tasks, STAMP);
+        for line in text.split_terminator("\r\n") {
+            assert!(line.len() <= 75, "{line}");
+        }
+        assert!(text.contains("\r\n "));
+        assert_eq!(json(&round_trip(&tasks).tasks), json(&tasks));
+    }
+
+    #[test]
+    fn escapes_text() {
+        let tasks = vec![TaskData {
+            notes: "First line\nSecond line\\".to_owned(),
+            ..task("a", "Milk, eggs; bread")
+        }];
+
+        let text = export("Shopping, weekly", &tasks, STAMP);
+        assert!(text.contains("X-WR-CALNAME:Shopping\\, weekly\r\n"));
+        assert!(text.contains("SUMMARY:Milk\\, eggs\\; bread\r\n"));
+        assert!(text.contains("DESCRIPTION:First line\\nSecond line\\\\\r\n"));
+
+        let calendar = parse_utc_as_local(&text).unwrap();
+        assert_eq!(calendar.name.as_deref(), Some("Shopping, weekly"));
+        assert_eq!(json(&calendar.tasks), json(&tasks));
+    }
+
+    #[test]
+    fn reads_escapes_and_lists_of_other_clients() {
+        let text = "BEGIN:VCALENDAR\r\n\
+                    BEGIN:VTODO\r\n\
+                    UID:a\r\n\
+                    SUMMARY:One\\Ntwo\\, three\r\n\
+                    CATEGORIES:Home,Work Stuff,a\\,b\r\n\
+                    END:VTODO\r\n\
+                    END:VCALENDAR\r\n";
+
+        let task = &parse_utc_as_local(text).unwrap().tasks[0];
+        assert_eq!(task.content, "One\ntwo, three");
+        assert_eq!(task.tags, ["home", "work-stuff"]);
+    }
+
+    #[test]
+    fn leaves_out_times_that_are_no_dates() {
+        let tasks = vec![TaskData {
+            created_at: i64::MAX,
+            modified_at: i64::MIN,
+            ..task("a", "Odd times")
+        }];
+
+        let text = export("Chores", &tasks, STAMP);
+        assert!(!text.contains("CREATED"));
+        assert!(!text.contains("LAST-MODIFIED"));
+        assert_eq!(round_trip(&tasks).tasks[0].created_at, 0);
+    }
+
+    #[test]
+    fn rejects_broken_files() {
+        assert_eq!(
+            parse_utc_as_local("Not a calendar").unwrap_err(),
+            ParseError::NotICalendar
+        );
+        assert_eq!(
+            parse_utc_as_local("BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nEND:VCALENDAR\r\n").unwrap_err(),
+            ParseError::Unbalanced("VCALENDAR".to_owned())
+        );
+        assert_eq!(
+            parse_utc_as_local("BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\n").unwrap_err(),
+            ParseError::Unbalanced("VTODO".to_owned())
+        );
+        assert_eq!(
+            parse_utc_as_local("BEGIN:VCALENDAR\r\nnonsense\r\nEND:VCALENDAR\r\n").unwrap_err(),
+            ParseError::InvalidLine("nonsense".to_owned())
+        );
+    }
+}
@@ -1,4 +1,16 @@
+mod cli;
 mod config;
+mod date;
+mod dbus;
+mod ical;
+mod markdown;
+mod persistence;
+mod query;
+mod quick_add;
+mod recurrence;
+mod reminders;
+mod tags;
+mod todotxt;
 mod utils;
 mod widgets;
 
@@ -14,32 +26,120 @@ fn main() -> glib::ExitCode {
 
     // println!("{}", env!("GSETTINGS_SCHEMA_DIR"));
 
-    let app = adw::Application::builder().application_id(APP_ID).build();
+    // Command lines go to the primary instance, so commands run in a window
+    // that is already open instead of racing it for the data file
+    let app = adw::Application::builder()
+        .application_id(APP_ID)
+        .flags(gio::ApplicationFlags::HANDLES_COMMAND_LINE)
+        .build();
 
     app.connect_startup(on_startup);
     app.connect_activate(build_ui);
+    app.connect_command_line(|app, command_line| on_command_line(app, command_line).value());
 
     app.run()
 }
 
 fn on_startup(app: &adw::Application) {
+    setup_actions(app);
+    dbus::export(app);
+
+    // Command line calls can run without a display, and don't need the styles
+    let Some(display) = gtk::gdk::Display::default() else {
+        return;
+    };
     let css_provider = gtk::CssProvider::new();
     css_provider.load_from_resource("/com/github/shahradelahi/Todo/style.css");
 
     gtk::style_context_add_provider_for_display(
-        &gtk::gdk::Display::default().expect("Could not connect to a display."),
+        &display,
         &css_provider,
         gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
     );
 }
 
+fn setup_actions(app: &adw::Application) {
+    // Both are triggered from due-task notifications, with the task ID as target
+    let action_mark_done = gio::ActionEntry::builder("mark-done")
+        .parameter_type(Some(&String::static_variant_type()))
+        .activate(|app: &adw::Application, _, parameter| {
+            let id = task_id_parameter(parameter);
+            if let Some(task) = windows(app).find_map(|window| window.find_task(&id)) {
+                task.set_completed(true);
+            }
+        })
+        .build();
+
+    let action_snooze = gio::ActionEntry::builder("snooze")
+        .parameter_type(Some(&String::static_variant_type()))
+        .activate(|app: &adw::Application, _, parameter| {
+            let id = task_id_parameter(parameter);
+            if let Some(window) = windows(app).find(|window| window.find_task(&id).is_some()) {
+                window.snooze_task(&id);
+            }
+        })
+        .build();
+
+    app.add_action_entries([action_mark_done, action_snooze]);
+}
+
+/// Runs a command from the command line, or opens the window without one.
+/// Without a window the app quits right after, so no GUI shows up.
+fn on_command_line(
+    app: &adw::Application,
+    command_line: &gio::ApplicationCommandLine,
+) -> glib::ExitCode {
+    let arguments = command_line.arguments();
+    let command = match cli::parse(arguments.get(1..).unwrap_or_default()) {
+        Ok(Some(command)) => command,
+        Ok(None) => {
+            app.activate();
+            return glib::ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            command_line.printerr_literal(&format!("{err}\n\n{}", cli::USAGE));
+            return glib::ExitCode::FAILURE;
+        }
+    };
+
+    // An open window owns the tasks and saves them itself
+    let result = match windows(app).next() {
+        Some(window) => cli::run(&command, &window.collections()),
+        None => cli::run_on_data_file(&command),
+    };
+    match result {
+        Ok(output) => {
+            command_line.print_literal(&output);
+            glib::ExitCode::SUCCESS
+        }
+        Err(err) => {
+            command_line.printerr_literal(&format!("{err}\n"));
+            glib::ExitCode::FAILURE
+        }
+    }
+}
+
+fn task_id_parameter(parameter: Option<&glib::Variant>) -> String {
+    parameter
+        .and_then(String::from_variant)
+        .expect("Task ID parameter is not a string")
+}
+
+fn windows(app: &adw::Application) -> impl Iterator<Item = Window> {
+    app.windows()
+        .into_iter()
+        .filter_map(|window| window.downcast::<Window>().ok())
+}
+
 fn build_ui(app: &adw::Application) {
     let window = Window::new(app);
+    dbus::watch_window(app, &window);
 
     app.set_accels_for_action("win.filter('All')", &["<Ctrl>a"]);
     app.set_accels_for_action("win.filter('Open')", &["<Ctrl>o"]);
     app.set_accels_for_action("win.filter('Done')", &["<Ctrl>d"]);
     app.set_accels_for_action("win.close", &["<Ctrl>W"]);
+    app.set_accels_for_action("win.toggle-search", &["<Ctrl>f"]);
 
     window.present();
 }
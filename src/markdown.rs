@@ -0,0 +1,266 @@
+use std::fmt::Write;
+
+use crate::widgets::{CollectionData, TaskData};
+
+/// How a stretch of Markdown is shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// `#` to `######`, the whole line
+    Heading(u8),
+    /// `- `, `* `, `+ ` or `1. ` at the start of a line, the marker only
+    ListMarker,
+    Bold,
+    Italic,
+    Strikethrough,
+    Code,
+}
+
+/// A styled stretch, in characters rather than bytes since that is what
+/// text buffers count in. Delimiters like `**` are part of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub style: Style,
+}
+
+/// Finds the basic Markdown in `text`: headings, list markers, and inline
+/// bold, italic, strikethrough and code. Inline markup doesn't cross lines.
+pub fn highlight(text: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut line_start = 0;
+
+    for line in text.split('\n') {
+        let chars = line.chars().collect::<Vec<_>>();
+        let line_spans = block_span(&chars)
+            .into_iter()
+            .chain(inline_spans(&chars))
+            .map(|span| Span {
+                start: span.start + line_start,
+                end: span.end + line_start,
+                style: span.style,
+            });
+        spans.extend(line_spans);
+
+        // The newline counts as a character too
+        line_start += chars.len() + 1;
+    }
+
+    spans
+}
+
+/// Writes `collection` as a heading followed by a task list, subtasks
+/// indented under their task. Line breaks in a task become spaces so every
+/// task stays one item, and the list reads back with `parse_checklist`.
+pub fn checklist(collection: &CollectionData) -> String {
+    let mut text = format!("# {}\n\n", collection.title);
+    for task in &collection.tasks_data {
+        write_checklist_item(&mut text, task, 0);
+    }
+    text
+}
+
+fn write_checklist_item(text: &mut String, task: &TaskData, depth: usize) {
+    let check = if task.completed { 'x' } else { ' ' };
+    let content = task
+        .content
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    writeln!(
+        text,
+        "{:indent$}- [{check}] {content}",
+        "",
+        indent = depth * 2
+    )
+    .unwrap();
+    for subtask in &task.subtasks {
+        write_checklist_item(text, subtask, depth + 1);
+    }
+}
+
+/// Reads text made only of task list items like `- [ ] Buy milk` or
+/// `* [x] Call mom`, as whether each is done and its text. Blank lines are
+/// skipped and nesting is flattened. `None` if any other line is in there.
+pub fn parse_checklist(text: &str) -> Option<Vec<(bool, String)>> {
+    let items = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let rest = line.trim_start().strip_prefix(['-', '*', '+'])?;
+            let rest = rest.strip_prefix(' ')?.trim_start();
+            let completed = match rest.get(..3)? {
+                "[ ]" => false,
+                "[x]" | "[X]" => true,
+                _ => return None,
+            };
+            let content = rest[3..].trim();
+            (!content.is_empty()).then(|| (completed, content.to_owned()))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    (!items.is_empty()).then_some(items)
+}
+
+fn block_span(line: &[char]) -> Option<Span> {
+    let level = line.iter().take_while(|&&c| c == '#').count();
+    if (1..=6).contains(&level) && line.get(level) == Some(&' ') {
+        return Some(Span {
+            start: 0,
+            end: line.len(),
+            style: Style::Heading(level as u8),
+        });
+    }
+
+    let indent = line.iter().take_while(|c| c.is_whitespace()).count();
+    let rest = &line[indent..];
+    let digits = rest.iter().take_while(|c| c.is_ascii_digit()).count();
+    let marker_len = match rest {
+        ['-' | '*' | '+', ' ', ..] => 1,
+        _ if digits > 0 && rest[digits..].starts_with(&['.', ' ']) => digits + 1,
+        _ => return None,
+    };
+    Some(Span {
+        start: indent,
+        end: indent + marker_len,
+        style: Style::ListMarker,
+    })
+}
+
+fn inline_spans(line: &[char]) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut index = 0;
+
+    while index < line.len() {
+        let rest = &line[index..];
+        let (delimiter, style): (&[char], _) = match rest {
+            ['`', ..] => (&['`'], Style::Code),
+            ['*', '*', ..] => (&['*', '*'], Style::Bold),
+            ['~', '~', ..] => (&['~', '~'], Style::Strikethrough),
+            ['*' | '_', ..] => (&rest[..1], Style::Italic),
+            _ => {
+                index += 1;
+                continue;
+            }
+        };
+
+        let inner_start = index + delimiter.len();
+        match find(&line[inner_start..], delimiter) {
+            // Empty pairs like `**` on their own are left alone
+            Some(inner_len) if inner_len > 0 => {
+                let end = inner_start + inner_len + delimiter.len();
+                spans.push(Span {
+                    start: index,
+                    end,
+                    style,
+                });
+                index = end;
+            }
+            _ => index += delimiter.len(),
+        }
+    }
+
+    spans
+}
+
+fn find(haystack: &[char], needle: &[char]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(content: &str, completed: bool, subtasks: Vec<TaskData>) -> TaskData {
+        TaskData {
+            content: content.to_owned(),
+            completed,
+            subtasks,
+            ..TaskData::default()
+        }
+    }
+
+    fn item(completed: bool, content: &str) -> (bool, String) {
+        (completed, content.to_owned())
+    }
+
+    fn groceries() -> CollectionData {
+        CollectionData {
+            id: String::new(),
+            title: "Groceries".to_owned(),
+            tasks_data: vec![
+                task(
+                    "Bake a cake",
+                    false,
+                    vec![task(
+                        "Flour",
+                        true,
+                        vec![task("The **fine** one", false, vec![])],
+                    )],
+                ),
+                // Like an imported iCalendar SUMMARY
+                task("Milk\nthe oat one,\r\n  not soy", true, vec![]),
+            ],
+        }
+    }
+
+    #[test]
+    fn writes_checklists() {
+        assert_eq!(
+            checklist(&groceries()),
+            "# Groceries\n\
+             \n\
+             - [ ] Bake a cake\n\
+             \x20 - [x] Flour\n\
+             \x20   - [ ] The **fine** one\n\
+             - [x] Milk the oat one, not soy\n"
+        );
+    }
+
+    #[test]
+    fn checklists_paste_back() {
+        let text = checklist(&groceries());
+        // Pasting leaves out the heading, which is no task
+        let (_, list) = text.split_once("\n\n").unwrap();
+        assert_eq!(
+            parse_checklist(list),
+            Some(vec![
+                item(false, "Bake a cake"),
+                item(true, "Flour"),
+                item(false, "The **fine** one"),
+                item(true, "Milk the oat one, not soy"),
+            ])
+        );
+        assert_eq!(parse_checklist(&text), None);
+    }
+
+    #[test]
+    fn reads_done_items() {
+        let text = "* [x] Call mom\n\n+ [X]   Water plants every day  \n- [ ] Pay rent";
+        assert_eq!(
+            parse_checklist(text),
+            Some(vec![
+                item(true, "Call mom"),
+                item(true, "Water plants every day"),
+                item(false, "Pay rent"),
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_anything_else() {
+        for text in [
+            "",
+            "\n  \n",
+            "- [ ] Pay rent\nand the bills",
+            "- Pay rent",
+            "-[ ] Pay rent",
+            "- [ ]",
+            "- [o] Pay rent",
+            "1. [ ] Pay rent",
+        ] {
+            assert_eq!(parse_checklist(text), None, "{text:?}");
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::rc::Rc;
+use std::time::Duration;
+
+use adw::prelude::*;
+use glib::{clone, WeakRef};
+use gtk::{gio, glib};
+
+use crate::config::LOG_DOMAIN;
+use crate::widgets::{CollectionObject, SavedFilterObject, TaskObject};
+
+/// How long to wait after the last change before a snapshot is written.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+struct WatchedTask {
+    task: TaskObject,
+    handlers: Vec<glib::SignalHandlerId>,
+    subtasks: WatchedStore,
+}
+
+impl WatchedTask {
+    fn disconnect(self) {
+        for handler_id in self.handlers {
+            self.task.disconnect(handler_id);
+        }
+        self.subtasks.disconnect();
+    }
+}
+
+/// A store of tasks, either a collection's tasks or a task's subtasks.
+struct WatchedStore {
+    store: gio::ListStore,
+    handler: glib::SignalHandlerId,
+    // Mirrors the order of `store` so removals can be disconnected
+    tasks: Rc<RefCell<Vec<WatchedTask>>>,
+}
+
+impl WatchedStore {
+    fn disconnect(self) {
+        self.store.disconnect(self.handler);
+        for watched_task in self.tasks.take() {
+            watched_task.disconnect();
+        }
+    }
+}
+
+struct WatchedCollection {
+    collection: CollectionObject,
+    title_handler: glib::SignalHandlerId,
+    tasks: WatchedStore,
+}
+
+impl WatchedCollection {
+    fn disconnect(self) {
+        self.collection.disconnect(self.title_handler);
+        self.tasks.disconnect();
+    }
+}
+
+struct WatchedSavedFilter {
+    saved_filter: SavedFilterObject,
+    handlers: Vec<glib::SignalHandlerId>,
+}
+
+impl WatchedSavedFilter {
+    fn disconnect(self) {
+        for handler_id in self.handlers {
+            self.saved_filter.disconnect(handler_id);
+        }
+    }
+}
+
+type TaskCallback = Box<dyn Fn(&TaskObject)>;
+type ErrorCallback = Box<dyn Fn(&io::Error)>;
+
+/// Writes debounced snapshots of the collections and saved filters whenever
+/// they change.
+pub struct Autosave {
+    collections: gio::ListStore,
+    saved_filters: gio::ListStore,
+    pending: RefCell<Option<glib::SourceId>>,
+    enabled: Cell<bool>,
+    on_change: RefCell<Vec<Box<dyn Fn()>>>,
+    on_task_change: RefCell<Vec<TaskCallback>>,
+    on_save_error: RefCell<Vec<ErrorCallback>>,
+    // Mirrors the order of `collections` so removals can be disconnected
+    watched: RefCell<Vec<WatchedCollection>>,
+    // Likewise for `saved_filters`
+    watched_saved_filters: RefCell<Vec<WatchedSavedFilter>>,
+}
+
+impl Autosave {
+    pub fn new(collections: &gio::ListStore, saved_filters: &gio::ListStore) -> Rc<Self> {
+        let autosave = Rc::new(Self {
+            collections: collections.clone(),
+            saved_filters: saved_filters.clone(),
+            pending: RefCell::default(),
+            enabled: Cell::new(true),
+            on_change: RefCell::default(),
+            on_task_change: RefCell::default(),
+            on_save_error: RefCell::default(),
+            watched: RefCell::default(),
+            watched_saved_filters: RefCell::default(),
+        });
+
+        autosave.collections_changed(0, 0, collections.n_items());
+        collections.connect_items_changed(clone!(
+            #[weak]
+            autosave,
+            move |_, position, removed, added| {
+                autosave.collections_changed(position, removed, added);
+                autosave.schedule();
+            }
+        ));
+
+        autosave.saved_filters_changed(0, 0, saved_filters.n_items());
+        saved_filters.connect_items_changed(clone!(
+            #[weak]
+            autosave,
+            move |_, position, removed, added| {
+                autosave.saved_filters_changed(position, removed, added);
+                autosave.schedule();
+            }
+        ));
+
+        autosave
+    }
+
+    /// Cancels any pending snapshot and writes one right away, unless saving
+    /// is disabled.
+    pub fn flush(&self) -> io::Result<()> {
+        if let Some(source_id) = self.pending.take() {
+            source_id.remove();
+        }
+        if !self.enabled.get() {
+            return Ok(());
+        }
+        super::save(&self.collections, &self.saved_filters)
+    }
+
+    /// Stops or resumes writing snapshots. Changes are still reported while
+    /// disabled, only the data file is left alone.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.set(enabled);
+        if !enabled {
+            if let Some(source_id) = self.pending.take() {
+                source_id.remove();
+            }
+        }
+    }
+
+    /// Calls `callback` right away whenever something that gets saved changes.
+    pub fn connect_changed(&self, callback: impl Fn() + 'static) {
+        self.on_change.borrow_mut().push(Box::new(callback));
+    }
+
+    /// Calls `callback` with a top-level task whenever it, or one of its
+    /// subtasks, is added, edited or removed.
+    pub fn connect_task_changed(&self, callback: impl Fn(&TaskObject) + 'static) {
+        self.on_task_change.borrow_mut().push(Box::new(callback));
+    }
+
+    /// Calls `callback` whenever a snapshot in the background could not be
+    /// written.
+    pub fn connect_save_error(&self, callback: impl Fn(&io::Error) + 'static) {
+        self.on_save_error.borrow_mut().push(Box::new(callback));
+    }
+
+    fn task_changed(&self, task: &TaskObject) {
+        for callback in self.on_task_change.borrow().iter() {
+            callback(task);
+        }
+    }
+
+    fn schedule(self: &Rc<Self>) {
+        for callback in self.on_change.borrow().iter() {
+            callback();
+        }
+
+        if let Some(source_id) = self.pending.take() {
+            source_id.remove();
+        }
+        if !self.enabled.get() {
+            return;
+        }
+
+        let source_id = glib::timeout_add_local_once(
+            DEBOUNCE,
+            clone!(
+                #[weak(rename_to = autosave)]
+                self,
+                move || {
+                    autosave.pending.take();
+                    if let Err(err) = super::save(&autosave.collections, &autosave.saved_filters) {
+                        glib::g_warning!(LOG_DOMAIN, "Could not save collections: {err}");
+                        for callback in autosave.on_save_error.borrow().iter() {
+                            callback(&err);
+                        }
+                    }
+                }
+            ),
+        );
+        self.pending.replace(Some(source_id));
+    }
+
+    fn collections_changed(self: &Rc<Self>, position: u32, removed: u32, added: u32) {
+        let added = (position..position + added)
+            .filter_map(|index| self.collections.item(index).and_downcast())
+            .map(|collection| self.watch_collection(collection))
+            .collect::<Vec<_>>();
+
+        let range = position as usize..(position + removed) as usize;
+        let removed = self
+            .watched
+            .borrow_mut()
+            .splice(range, added)
+            .collect::<Vec<_>>();
+        for watched_collection in removed {
+            watched_collection.disconnect();
+        }
+    }
+
+    fn saved_filters_changed(self: &Rc<Self>, position: u32, removed: u32, added: u32) {
+        let added = (position..position + added)
+            .filter_map(|index| self.saved_filters.item(index).and_downcast())
+            .map(|saved_filter| self.watch_saved_filter(saved_filter))
+            .collect::<Vec<_>>();
+
+        let range = position as usize..(position + removed) as usize;
+        let removed = self
+            .watched_saved_filters
+            .borrow_mut()
+            .splice(range, added)
+            .collect::<Vec<_>>();
+        for watched_saved_filter in removed {
+            watched_saved_filter.disconnect();
+        }
+    }
+
+    fn watch_saved_filter(self: &Rc<Self>, saved_filter: SavedFilterObject) -> WatchedSavedFilter {
+        let handlers = ["name", "query"]
+            .into_iter()
+            .map(|property| {
+                saved_filter.connect_notify_local(
+                    Some(property),
+                    clone!(
+                        #[weak(rename_to = autosave)]
+                        self,
+                        move |_, _| {
+                            autosave.schedule();
+                        }
+                    ),
+                )
+            })
+            .collect();
+
+        WatchedSavedFilter {
+            saved_filter,
+            handlers,
+        }
+    }
+
+    fn watch_collection(self: &Rc<Self>, collection: CollectionObject) -> WatchedCollection {
+        let title_handler = collection.connect_title_notify(clone!(
+            #[weak(rename_to = autosave)]
+            self,
+            move |_| {
+                autosave.schedule();
+            }
+        ));
+
+        WatchedCollection {
+            tasks: self.watch_store(collection.tasks(), None),
+            collection,
+            title_handler,
+        }
+    }
+
+    /// `top_level` is the task `store` holds the subtasks of, directly or
+    /// further down, `None` for a collection's tasks.
+    fn watch_store(
+        self: &Rc<Self>,
+        store: gio::ListStore,
+        top_level: Option<WeakRef<TaskObject>>,
+    ) -> WatchedStore {
+        let tasks = Rc::new(RefCell::new(
+            store
+                .iter::<TaskObject>()
+                .filter_map(Result::ok)
+                .map(|task| self.watch_task(task, top_level.clone()))
+                .collect::<Vec<_>>(),
+        ));
+
+        let handler = store.connect_items_changed(clone!(
+            #[weak(rename_to = autosave)]
+            self,
+            #[strong]
+            tasks,
+            move |store, position, removed, added| {
+                let added = (position..position + added)
+                    .filter_map(|index| store.item(index).and_downcast())
+                    .collect::<Vec<TaskObject>>();
+                let watched = added
+                    .iter()
+                    .map(|task| autosave.watch_task(task.clone(), top_level.clone()))
+                    .collect::<Vec<_>>();
+
+                let range = position as usize..(position + removed) as usize;
+                let removed = tasks
+                    .borrow_mut()
+                    .splice(range, watched)
+                    .collect::<Vec<_>>();
+
+                // Subtasks coming and going are a change to their top-level task
+                let changed = match top_level.as_ref().and_then(WeakRef::upgrade) {
+                    Some(task) => vec![task],
+                    None => removed
+                        .iter()
+                        .map(|watched_task| watched_task.task.clone())
+                        .chain(added)
+                        .collect(),
+                };
+                for watched_task in removed {
+                    watched_task.disconnect();
+                }
+
+                for task in &changed {
+                    autosave.task_changed(task);
+                }
+                autosave.schedule();
+            }
+        ));
+
+        WatchedStore {
+            store,
+            handler,
+            tasks,
+        }
+    }
+
+    fn watch_task(
+        self: &Rc<Self>,
+        task: TaskObject,
+        top_level: Option<WeakRef<TaskObject>>,
+    ) -> WatchedTask {
+        let handlers = [
+            "completed",
+            "content",
+            "due-date",
+            "due-time",
+            "priority",
+            "tags",
+            "recurrence",
+            "notes",
+        ]
+        .into_iter()
+        .map(|property| {
+            task.connect_notify_local(
+                Some(property),
+                clone!(
+                    #[weak(rename_to = autosave)]
+                    self,
+                    #[strong]
+                    top_level,
+                    move |task, _| {
+                        let top_level = top_level.as_ref().and_then(WeakRef::upgrade);
+                        autosave.task_changed(top_level.as_ref().unwrap_or(task));
+                        autosave.schedule();
+                    }
+                ),
+            )
+        })
+        .collect();
+
+        let subtasks_top_level = top_level.or_else(|| Some(task.downgrade()));
+        WatchedTask {
+            subtasks: self.watch_store(task.subtasks(), subtasks_top_level),
+            task,
+            handlers,
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use std::fmt;
+
+use gtk::glib;
+use serde_json::{json, Map, Value};
+
+/// Version of the envelope written by this build.
+pub const CURRENT_VERSION: u64 = 11;
+
+/// Each entry upgrades a document from the version equal to its index to the
+/// next one. Append a step here whenever the on-disk shape changes.
+const MIGRATIONS: &[fn(Value) -> Value] = &[
+    v0_to_v1, v1_to_v2, v2_to_v3, v3_to_v4, v4_to_v5, v5_to_v6, v6_to_v7, v7_to_v8, v8_to_v9,
+    v9_to_v10, v10_to_v11,
+];
+
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The document was written by a newer version of the app.
+    Unsupported(u64),
+    /// The document is neither a bare collection list nor a versioned envelope.
+    Unrecognized,
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Unsupported(version) => {
+                write!(f, "created by a newer version (data version {version})")
+            }
+            MigrationError::Unrecognized => write!(f, "unrecognized data layout"),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+/// Upgrades a parsed document of any known version to `CURRENT_VERSION`.
+pub fn migrate(mut document: Value) -> Result<Value, MigrationError> {
+    let mut version = version_of(&document)?;
+    if version > CURRENT_VERSION {
+        return Err(MigrationError::Unsupported(version));
+    }
+
+    while version < CURRENT_VERSION {
+        document = MIGRATIONS[version as usize](document);
+        version += 1;
+    }
+
+    Ok(document)
+}
+
+fn version_of(document: &Value) -> Result<u64, MigrationError> {
+    match document {
+        // Before the envelope existed the file was a bare list of collections
+        Value::Array(_) => Ok(0),
+        Value::Object(envelope) => envelope
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or(MigrationError::Unrecognized),
+        _ => Err(MigrationError::Unrecognized),
+    }
+}
+
+/// Wraps the bare collection list in a versioned envelope.
+fn v0_to_v1(document: Value) -> Value {
+    json!({
+        "version": 1,
+        "collections": document,
+    })
+}
+
+/// Gives every task an explicit, empty due date and time.
+fn v1_to_v2(mut document: Value) -> Value {
+    for_each_task(&mut document, |task| {
+        task.insert("due_date".into(), Value::Null);
+        task.insert("due_time".into(), Value::Null);
+    });
+    set_version(&mut document, 2);
+    document
+}
+
+/// Gives every task a stable ID.
+fn v2_to_v3(mut document: Value) -> Value {
+    for_each_task(&mut document, |task| {
+        task.entry("id")
+            .or_insert_with(|| glib::uuid_string_random().as_str().into());
+    });
+    set_version(&mut document, 3);
+    document
+}
+
+/// Gives every collection a stable ID.
+fn v3_to_v4(mut document: Value) -> Value {
+    for_each_collection(&mut document, |collection| {
+        collection
+            .entry("id")
+            .or_insert_with(|| glib::uuid_string_random().as_str().into());
+    });
+    set_version(&mut document, 4);
+    document
+}
+
+/// Gives every task an empty list of subtasks.
+fn v4_to_v5(mut document: Value) -> Value {
+    for_each_task(&mut document, |task| {
+        task.insert("subtasks".into(), json!([]));
+    });
+    set_version(&mut document, 5);
+    document
+}
+
+/// Gives every task, subtasks included, no priority.
+fn v5_to_v6(mut document: Value) -> Value {
+    for_each_task(&mut document, |task| {
+        task.insert("priority".into(), "none".into());
+    });
+    set_version(&mut document, 6);
+    document
+}
+
+/// Records unknown creation and modification times on every task.
+fn v6_to_v7(mut document: Value) -> Value {
+    for_each_task(&mut document, |task| {
+        task.insert("created_at".into(), 0.into());
+        task.insert("modified_at".into(), 0.into());
+    });
+    set_version(&mut document, 7);
+    document
+}
+
+/// Gives every task an empty list of tags.
+fn v7_to_v8(mut document: Value) -> Value {
+    for_each_task(&mut document, |task| {
+        task.insert("tags".into(), json!([]));
+    });
+    set_version(&mut document, 8);
+    document
+}
+
+/// Makes every task non-recurring, with no completions recorded.
+fn v8_to_v9(mut document: Value) -> Value {
+    for_each_task(&mut document, |task| {
+        task.insert("recurrence".into(), Value::Null);
+        task.insert("completions".into(), json!([]));
+    });
+    set_version(&mut document, 9);
+    document
+}
+
+/// Gives every task empty notes.
+fn v9_to_v10(mut document: Value) -> Value {
+    for_each_task(&mut document, |task| {
+        task.insert("notes".into(), "".into());
+    });
+    set_version(&mut document, 10);
+    document
+}
+
+/// Adds an empty list of saved filters next to the collections.
+fn v10_to_v11(mut document: Value) -> Value {
+    if let Some(envelope) = document.as_object_mut() {
+        envelope.insert("saved_filters".into(), json!([]));
+    }
+    set_version(&mut document, 11);
+    document
+}
+
+fn set_version(document: &mut Value, version: u64) {
+    if let Some(envelope) = document.as_object_mut() {
+        envelope.insert("version".into(), version.into());
+    }
+}
+
+fn for_each_collection(document: &mut Value, f: impl FnMut(&mut Map<String, Value>)) {
+    document
+        .get_mut("collections")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+        .for_each(f);
+}
+
+/// Visits every task of every collection, then its subtasks, if it has any.
+fn for_each_task(document: &mut Value, mut f: impl FnMut(&mut Map<String, Value>)) {
+    for_each_collection(document, |collection| {
+        visit_tasks(collection.get_mut("tasks_data"), &mut f);
+    });
+}
+
+fn visit_tasks(tasks: Option<&mut Value>, f: &mut impl FnMut(&mut Map<String, Value>)) {
+    let tasks = tasks
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut);
+    for task in tasks {
+        f(task);
+        visit_tasks(task.get_mut("subtasks"), f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What each version of the app wrote for the same small set of tasks,
+    /// subtasks from version 5 on.
+    const FIXTURES: [&str; CURRENT_VERSION as usize] = [
+        include_str!("fixtures/v0.json"),
+        include_str!("fixtures/v1.json"),
+        include_str!("fixtures/v2.json"),
+        include_str!("fixtures/v3.json"),
+        include_str!("fixtures/v4.json"),
+        include_str!("fixtures/v5.json"),
+        include_str!("fixtures/v6.json"),
+        include_str!("fixtures/v7.json"),
+        include_str!("fixtures/v8.json"),
+        include_str!("fixtures/v9.json"),
+        include_str!("fixtures/v10.json"),
+    ];
+
+    #[test]
+    fn every_version_loads() {
+        for (version, fixture) in FIXTURES.iter().enumerate() {
+            let document = serde_json::from_str(fixture).unwrap();
+            let document = migrate(document)
+                .unwrap_or_else(|err| panic!("version {version} does not migrate: {err}"));
+            assert_eq!(document["version"], CURRENT_VERSION);
+
+            let data = crate::persistence::parse(fixture.as_bytes())
+                .unwrap_or_else(|err| panic!("version {version} does not load: {err}"));
+            assert_eq!(data.collections.len(), 1);
+            assert!(data.saved_filters.is_empty());
+
+            let collection = &data.collections[0];
+            assert_eq!(collection.title, "Home");
+            assert!(!collection.id.is_empty());
+            let contents = collection
+                .tasks_data
+                .iter()
+                .map(|task| task.content.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(contents, ["Buy milk", "Water plants"]);
+            assert!(collection.tasks_data.iter().all(|task| !task.id.is_empty()));
+
+            // Saving and loading again changes nothing, ids handed out included
+            let saved = crate::persistence::serialize(data).unwrap();
+            let reloaded = crate::persistence::parse(&saved)
+                .unwrap_or_else(|err| panic!("version {version} does not reload: {err}"));
+            let resaved = crate::persistence::serialize(reloaded).unwrap();
+            assert_eq!(
+                serde_json::from_slice::<Value>(&resaved).unwrap(),
+                serde_json::from_slice::<Value>(&saved).unwrap(),
+                "version {version} does not round trip"
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_what_older_versions_stored() {
+        let data = crate::persistence::parse(FIXTURES[2].as_bytes()).unwrap();
+        let task = &data.collections[0].tasks_data[0];
+        assert_eq!(task.due_date.as_deref(), Some("2026-10-20"));
+        assert_eq!(task.due_time.as_deref(), Some("09:00"));
+
+        let data = crate::persistence::parse(FIXTURES[5].as_bytes()).unwrap();
+        let subtask = &data.collections[0].tasks_data[0].subtasks[0];
+        assert_eq!(subtask.content, "Check the fridge");
+        assert_eq!(subtask.id, "7e6d5c4b-3a29-4817-a6f5-e4d3c2b1a098");
+        assert!(subtask.tags.is_empty());
+        assert_eq!(subtask.notes, "");
+    }
+
+    #[test]
+    fn current_version_is_left_alone() {
+        let document = json!({
+            "version": CURRENT_VERSION,
+            "collections": [],
+            "saved_filters": [{ "id": "1", "name": "Soon", "query": "due:7d" }],
+        });
+        assert_eq!(migrate(document.clone()).unwrap(), document);
+    }
+
+    #[test]
+    fn newer_version_is_unsupported() {
+        let document = json!({ "version": CURRENT_VERSION + 1, "collections": [] });
+        assert!(matches!(
+            migrate(document),
+            Err(MigrationError::Unsupported(version)) if version == CURRENT_VERSION + 1
+        ));
+
+        let err = crate::persistence::parse(br#"{"version": 999, "collections": []}"#)
+            .err()
+            .unwrap();
+        assert!(err.is_from_newer_version());
+    }
+
+    #[test]
+    fn unrecognized_layouts_are_rejected() {
+        for document in [json!("tasks"), json!({ "collections": [] }), json!(3)] {
+            assert!(matches!(
+                migrate(document),
+                Err(MigrationError::Unrecognized)
+            ));
+        }
+    }
+}
@@ -0,0 +1,170 @@
+mod autosave;
+mod migrations;
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use adw::prelude::*;
+use gtk::{gio, glib};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::data_path;
+use crate::widgets::{CollectionData, CollectionObject, SavedFilterData, SavedFilterObject};
+use migrations::{MigrationError, CURRENT_VERSION};
+
+pub use autosave::Autosave;
+
+/// The envelope every data file is wrapped in, so older layouts can be told
+/// apart and migrated.
+#[derive(Serialize, Deserialize)]
+struct Document {
+    version: u64,
+    collections: Vec<CollectionData>,
+    saved_filters: Vec<SavedFilterData>,
+}
+
+/// Everything that is stored in the data file.
+#[derive(Default)]
+pub struct Data {
+    pub collections: Vec<CollectionData>,
+    pub saved_filters: Vec<SavedFilterData>,
+}
+
+/// Why the data file could not be turned back into collections and filters.
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    Migration(MigrationError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "could not read data file: {err}"),
+            LoadError::Parse(err) => write!(f, "data file is corrupt: {err}"),
+            LoadError::Migration(err) => write!(f, "data file could not be upgraded: {err}"),
+        }
+    }
+}
+
+impl LoadError {
+    /// Whether the file is fine but was written by a newer version, so it
+    /// must not be moved aside or written over.
+    pub fn is_from_newer_version(&self) -> bool {
+        matches!(self, LoadError::Migration(MigrationError::Unsupported(_)))
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io(err) => Some(err),
+            LoadError::Parse(err) => Some(err),
+            LoadError::Migration(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for LoadError {
+    fn from(err: serde_json::Error) -> Self {
+        LoadError::Parse(err)
+    }
+}
+
+impl From<MigrationError> for LoadError {
+    fn from(err: MigrationError) -> Self {
+        LoadError::Migration(err)
+    }
+}
+
+/// Reads the collections and filters stored at `data_path()`. A missing file
+/// is not an error, it just means there is nothing to restore yet.
+pub fn load() -> Result<Data, LoadError> {
+    let contents = match fs::read(data_path()) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Data::default()),
+        Err(err) => return Err(err.into()),
+    };
+
+    parse(&contents)
+}
+
+/// Parses a data file of any known version, migrating it to the current one.
+pub fn parse(contents: &[u8]) -> Result<Data, LoadError> {
+    let document = migrations::migrate(serde_json::from_slice(contents)?)?;
+    let document: Document = serde_json::from_value(document)?;
+    Ok(Data {
+        collections: document.collections,
+        saved_filters: document.saved_filters,
+    })
+}
+
+/// Serializes collections and filters into the current version of the data file.
+pub fn serialize(data: Data) -> serde_json::Result<Vec<u8>> {
+    serde_json::to_vec(&Document {
+        version: CURRENT_VERSION,
+        collections: data.collections,
+        saved_filters: data.saved_filters,
+    })
+}
+
+/// Renames the data file to a timestamped backup next to it, so a fresh one
+/// can be written without losing whatever was in the old one.
+pub fn move_aside() -> io::Result<PathBuf> {
+    let path = data_path();
+    let timestamp = glib::DateTime::now_local()
+        .and_then(|now| now.format("%Y%m%d-%H%M%S"))
+        .map_err(io::Error::other)?;
+
+    let mut backup_path = path.as_os_str().to_owned();
+    backup_path.push(format!(".{timestamp}.bak"));
+    let backup_path = PathBuf::from(backup_path);
+
+    fs::rename(&path, &backup_path)?;
+    Ok(backup_path)
+}
+
+/// Collects the serializable state of every collection and filter in the stores.
+pub fn snapshot(collections: &gio::ListStore, saved_filters: &gio::ListStore) -> Data {
+    Data {
+        collections: collections
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .map(|collection_object| collection_object.to_collection_data())
+            .collect(),
+        saved_filters: saved_filters
+            .iter::<SavedFilterObject>()
+            .filter_map(Result::ok)
+            .map(|saved_filter| saved_filter.to_saved_filter_data())
+            .collect(),
+    }
+}
+
+/// Writes the current collections and filters to `data_path()`.
+pub fn save(collections: &gio::ListStore, saved_filters: &gio::ListStore) -> io::Result<()> {
+    let json = serialize(snapshot(collections, saved_filters)).map_err(io::Error::other)?;
+    write_atomically(&data_path(), &json)
+}
+
+/// Writes `contents` to a sibling temp file and renames it over `path`, so
+/// readers only ever see the old or the new file, never a partial one.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+
+    fs::rename(&tmp_path, path)
+}
@@ -0,0 +1,406 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use gtk::prelude::*;
+use gtk::{glib, CustomFilter};
+
+use crate::date::Date;
+use crate::tags;
+use crate::utils::split_date_time;
+use crate::widgets::{Priority, TaskData, TaskObject};
+
+/// A search like `tag:work due:<7d -done priority>=high`, for saved filters.
+/// A task has to match every term, a leading `-` negates one. Terms are:
+///
+/// - `done` and `open`
+/// - `tag:name`, or `#name`
+/// - `due:` with an optional `<`, `<=`, `>`, `>=` or `=` and then `today`,
+///   `tomorrow`, `yesterday`, days or weeks from today like `7d` or `2w`, or
+///   a `YYYY-MM-DD` date. `due:none` is for tasks without a due date
+/// - `priority:` or `priority` and a comparison, then `none`, `low`,
+///   `medium`, `high` or `urgent`
+/// - any other word, which the content has to contain, ignoring case
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Query {
+    clauses: Vec<Clause>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Clause {
+    negated: bool,
+    term: Term,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Term {
+    Done,
+    Open,
+    Tag(String),
+    Due(Comparison, DueDate),
+    NoDue,
+    Priority(Comparison, Priority),
+    Text(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+/// Kept relative until the query runs, so saved queries move along with the days.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DueDate {
+    DaysFromToday(i64),
+    On(Date),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseQueryError {
+    term: String,
+}
+
+impl fmt::Display for ParseQueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "“{}” is not a valid search term", self.term)
+    }
+}
+
+impl std::error::Error for ParseQueryError {}
+
+impl FromStr for Query {
+    type Err = ParseQueryError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let clauses = text
+            .split_whitespace()
+            .map(|word| {
+                Clause::parse(word).ok_or_else(|| ParseQueryError {
+                    term: word.to_owned(),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Query { clauses })
+    }
+}
+
+impl Query {
+    /// Whether `task` matches every term, taking relative dates from `today`.
+    /// An empty query matches everything.
+    pub fn matches(&self, task: &TaskData, today: Date) -> bool {
+        self.clauses
+            .iter()
+            .all(|clause| clause.term.matches(task, today) != clause.negated)
+    }
+
+    /// A filter over `TaskObject`s, which looks at the clock on every run.
+    pub fn into_filter(self) -> CustomFilter {
+        CustomFilter::new(move |object| {
+            let task = object
+                .downcast_ref::<TaskObject>()
+                .expect("Must be task object");
+            let now = glib::DateTime::now_local().expect("Could not get the local time");
+            let (today, _) = split_date_time(&now);
+            task.matches(&self, today)
+        })
+    }
+}
+
+impl Clause {
+    fn parse(word: &str) -> Option<Self> {
+        // A lone "-" is just text
+        let (negated, word) = match word.strip_prefix('-') {
+            Some(rest) if !rest.is_empty() => (true, rest),
+            _ => (false, word),
+        };
+        let term = Term::parse(&word.to_lowercase())?;
+        Some(Clause { negated, term })
+    }
+}
+
+impl Term {
+    fn parse(word: &str) -> Option<Self> {
+        if word == "done" {
+            return Some(Term::Done);
+        }
+        if word == "open" {
+            return Some(Term::Open);
+        }
+        if let Some(name) = word.strip_prefix("tag:") {
+            return tags::parse(&format!("#{name}")).map(Term::Tag);
+        }
+        if word.starts_with('#') {
+            return tags::parse(word).map(Term::Tag);
+        }
+        if let Some(value) = word.strip_prefix("due:") {
+            if value == "none" {
+                return Some(Term::NoDue);
+            }
+            let (comparison, value) = Comparison::split(value);
+            return Some(Term::Due(comparison, DueDate::parse(value)?));
+        }
+        // Only with something after it, "priority" alone is a word like any other
+        if let Some(value) = word
+            .strip_prefix("priority")
+            .filter(|value| value.starts_with([':', '<', '>', '=']))
+        {
+            let value = value.strip_prefix(':').unwrap_or(value);
+            let (comparison, value) = Comparison::split(value);
+            return Some(Term::Priority(comparison, Priority::parse(value)?));
+        }
+        Some(Term::Text(word.to_owned()))
+    }
+
+    fn matches(&self, task: &TaskData, today: Date) -> bool {
+        let due_date = || task.due_date.as_deref().and_then(Date::parse);
+        match self {
+            Term::Done => task.completed,
+            Term::Open => !task.completed,
+            Term::Tag(tag) => task.tags.contains(tag),
+            Term::Due(comparison, date) => {
+                let (Some(due_date), Some(date)) = (due_date(), date.resolve(today)) else {
+                    return false;
+                };
+                comparison.holds(due_date.cmp(&date))
+            }
+            Term::NoDue => due_date().is_none(),
+            Term::Priority(comparison, priority) => comparison.holds(task.priority.cmp(priority)),
+            Term::Text(text) => task.content.to_lowercase().contains(text.as_str()),
+        }
+    }
+}
+
+impl Comparison {
+    /// Splits a leading operator off `text`, `Equal` when there is none.
+    fn split(text: &str) -> (Self, &str) {
+        let operators = [
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("=", Comparison::Equal),
+        ];
+        operators
+            .into_iter()
+            .find_map(|(operator, comparison)| Some((comparison, text.strip_prefix(operator)?)))
+            .unwrap_or((Comparison::Equal, text))
+    }
+
+    /// Whether `ordering`, of the task's value to the query's, satisfies it.
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Comparison::Less => ordering.is_lt(),
+            Comparison::LessOrEqual => ordering.is_le(),
+            Comparison::Equal => ordering.is_eq(),
+            Comparison::GreaterOrEqual => ordering.is_ge(),
+            Comparison::Greater => ordering.is_gt(),
+        }
+    }
+}
+
+impl DueDate {
+    fn parse(text: &str) -> Option<Self> {
+        let days = match text {
+            "today" => 0,
+            "tomorrow" => 1,
+            "yesterday" => -1,
+            _ => {
+                if let Some(days) = text.strip_suffix('d') {
+                    days.parse().ok()?
+                } else if let Some(weeks) = text.strip_suffix('w') {
+                    weeks.parse::<i64>().ok()?.checked_mul(7)?
+                } else {
+                    return Date::parse(text).map(DueDate::On);
+                }
+            }
+        };
+        Some(DueDate::DaysFromToday(days))
+    }
+
+    /// `None` when it is too far from `today` to be a date.
+    fn resolve(self, today: Date) -> Option<Date> {
+        match self {
+            DueDate::DaysFromToday(days) => today.checked_add_days(days),
+            DueDate::On(date) => Some(date),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> Date {
+        Date::new(year, month, day).unwrap()
+    }
+
+    /// A Tuesday.
+    fn today() -> Date {
+        date(2026, 10, 20)
+    }
+
+    fn task(content: &str, due_date: Option<&str>) -> TaskData {
+        TaskData {
+            content: content.to_owned(),
+            due_date: due_date.map(str::to_owned),
+            ..TaskData::default()
+        }
+    }
+
+    /// A few tasks, from overdue to next month.
+    fn tasks() -> Vec<TaskData> {
+        vec![
+            TaskData {
+                priority: Priority::Urgent,
+                tags: vec!["work".to_owned()],
+                ..task("Send Report", Some("2026-10-19"))
+            },
+            TaskData {
+                priority: Priority::High,
+                tags: vec!["work".to_owned(), "q3-plan".to_owned()],
+                ..task("Plan quarter", Some("2026-10-20"))
+            },
+            TaskData {
+                completed: true,
+                priority: Priority::Low,
+                tags: vec!["home".to_owned()],
+                ..task("Water plants", Some("2026-10-21"))
+            },
+            TaskData {
+                priority: Priority::Medium,
+                ..task("Book flights", Some("2026-10-27"))
+            },
+            task("Renew passport", Some("2026-11-20")),
+            task("Read a book", None),
+        ]
+    }
+
+    /// The contents of the tasks `query` matches.
+    fn matching(query: &str) -> Vec<String> {
+        let query = query.parse::<Query>().unwrap_or_else(|err| panic!("{err}"));
+        tasks()
+            .into_iter()
+            .filter(|task| query.matches(task, today()))
+            .map(|task| task.content)
+            .collect()
+    }
+
+    #[test]
+    fn empty_matches_everything() {
+        assert_eq!(matching("").len(), tasks().len());
+        assert_eq!(matching("   ").len(), tasks().len());
+    }
+
+    #[test]
+    fn done_and_open() {
+        assert_eq!(matching("done"), ["Water plants"]);
+        assert_eq!(matching("open").len(), 5);
+        assert_eq!(matching("-done"), matching("open"));
+    }
+
+    #[test]
+    fn tags() {
+        assert_eq!(matching("tag:work"), ["Send Report", "Plan quarter"]);
+        assert_eq!(matching("#Q3-plan"), ["Plan quarter"]);
+        assert_eq!(matching("#work -#q3-plan"), ["Send Report"]);
+        assert!(matching("tag:garden").is_empty());
+    }
+
+    #[test]
+    fn relative_due_dates() {
+        assert_eq!(matching("due:today"), ["Plan quarter"]);
+        assert_eq!(matching("due:tomorrow"), ["Water plants"]);
+        assert_eq!(matching("due:yesterday"), ["Send Report"]);
+        assert_eq!(matching("due:<today"), ["Send Report"]);
+        assert_eq!(matching("due:7d"), ["Book flights"]);
+        assert_eq!(matching("due:1w"), ["Book flights"]);
+        assert_eq!(
+            matching("due:>=today due:<=7d"),
+            ["Plan quarter", "Water plants", "Book flights"]
+        );
+        assert_eq!(
+            matching("due:<2w -done"),
+            ["Send Report", "Plan quarter", "Book flights"]
+        );
+        assert_eq!(matching("due:>2w"), ["Renew passport"]);
+        assert_eq!(matching("due:-1d"), ["Send Report"]);
+    }
+
+    #[test]
+    fn absolute_due_dates() {
+        assert_eq!(matching("due:2026-11-20"), ["Renew passport"]);
+        assert_eq!(
+            matching("due:>2026-10-21"),
+            ["Book flights", "Renew passport"]
+        );
+        assert_eq!(matching("due:=2026-10-19"), ["Send Report"]);
+    }
+
+    #[test]
+    fn no_due_date() {
+        assert_eq!(matching("due:none"), ["Read a book"]);
+        assert_eq!(matching("-due:none").len(), 5);
+        // Tasks without a due date are neither before nor after anything
+        assert!(!matching("due:<9999-01-01").contains(&"Read a book".to_owned()));
+    }
+
+    #[test]
+    fn priorities() {
+        assert_eq!(matching("priority:urgent"), ["Send Report"]);
+        assert_eq!(matching("priority>=high"), ["Send Report", "Plan quarter"]);
+        assert_eq!(
+            matching("priority:<medium"),
+            ["Water plants", "Renew passport", "Read a book"]
+        );
+        assert_eq!(matching("priority=none").len(), 2);
+    }
+
+    #[test]
+    fn text() {
+        assert_eq!(matching("report"), ["Send Report"]);
+        assert_eq!(matching("book"), ["Book flights", "Read a book"]);
+        assert_eq!(matching("book -read"), ["Book flights"]);
+        // A lone "-" and a bare "priority" are words like any other
+        assert!(matching("-").is_empty());
+        assert!(matching("priority").is_empty());
+    }
+
+    #[test]
+    fn everything_at_once() {
+        assert_eq!(
+            matching("tag:work due:<7d -done priority>=high plan"),
+            ["Plan quarter"]
+        );
+    }
+
+    #[test]
+    fn invalid_terms() {
+        for text in [
+            "due:soon",
+            "due:<",
+            "due:7x",
+            "due:2026-02-30",
+            "priority:highest",
+            "tag:",
+            "#",
+            "due:99999999999999999999w",
+        ] {
+            assert!(text.parse::<Query>().is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn far_off_dates_match_nothing() {
+        for text in [
+            "due:99999999999999999w",
+            "due:<9999999999999d",
+            "due:>-9999999999999d",
+        ] {
+            assert!(matching(text).is_empty(), "{text}");
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use crate::date::{Date, Time, Weekday};
+use crate::recurrence::{self, Recurrence};
+use crate::tags;
+use crate::widgets::Priority;
+
+/// The fields pulled out of text typed into the new task entry, like
+/// "Pay rent tomorrow 9am !high #home".
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QuickAdd {
+    /// Whatever is left once the recognized words are taken out
+    pub content: String,
+    pub due_date: Option<Date>,
+    pub due_time: Option<Time>,
+    pub priority: Priority,
+    pub tags: Vec<String>,
+    pub recurrence: Option<Recurrence>,
+}
+
+/// Parses `text` typed at `now` on `today`. Recognizes:
+///
+/// - dates: `today`, `tonight`, `tomorrow`, `next week`, a day name like
+///   `friday` or `fri` (today or the next one, optionally after `on`),
+///   `in 3 days`, `in 2 weeks` and `2026-10-20`
+/// - times: `9am`, `9:30pm`, `21:00`, `noon`, optionally after `at`
+/// - priorities: `!low`, `!medium`, `!high`, `!urgent`, or one to four `!`
+/// - tags: `#home`
+/// - recurrences: `daily`, `every month`, `every 2 weeks on mon,fri`,
+///   `every 3 days after completion` and the rest of what `Recurrence`
+///   displays as
+///
+/// Only the first date, time, priority and recurrence are taken, later ones
+/// stay in the content. A time without a date means the next time the clock
+/// shows it, a recurrence without one starts today or on its first weekday.
+pub fn parse(text: &str, today: Date, now: Time) -> QuickAdd {
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    let mut quick_add = QuickAdd::default();
+    let mut priority = None;
+    let mut recurrence_start = None;
+    let mut content = Vec::new();
+
+    let mut index = 0;
+    while index < words.len() {
+        let rest = &words[index..];
+
+        // Before dates, so "every monday" isn't taken for "monday"
+        if recurrence_start.is_none() {
+            if let Some((_, consumed)) = recurrence::parse_words(rest, today) {
+                recurrence_start = Some(index);
+                index += consumed;
+                continue;
+            }
+        }
+
+        if quick_add.due_date.is_none() {
+            if let Some((date, consumed)) = match_date(rest, today) {
+                quick_add.due_date = Some(date);
+                index += consumed;
+                continue;
+            }
+        }
+        if quick_add.due_time.is_none() {
+            if let Some((time, consumed)) = match_time(rest) {
+                quick_add.due_time = Some(time);
+                index += consumed;
+                continue;
+            }
+        }
+        if priority.is_none() {
+            if let Some(level) = parse_priority(rest[0]) {
+                priority = Some(level);
+                index += 1;
+                continue;
+            }
+        }
+        if let Some(tag) = tags::parse(rest[0]) {
+            if !quick_add.tags.contains(&tag) {
+                quick_add.tags.push(tag);
+            }
+            index += 1;
+            continue;
+        }
+
+        content.push(rest[0]);
+        index += 1;
+    }
+
+    if let (None, Some(time)) = (quick_add.due_date, quick_add.due_time) {
+        quick_add.due_date = Some(if time > now { today } else { today.add_days(1) });
+    }
+    if let Some(start) = recurrence_start {
+        // Parsed again now that the due date it is anchored on is known
+        let anchor = quick_add.due_date.unwrap_or(today);
+        let (recurrence, _) =
+            recurrence::parse_words(&words[start..], anchor).expect("Recurrence parsed before");
+        if quick_add.due_date.is_none() {
+            quick_add.due_date = Some(recurrence.first_due(today));
+        }
+        quick_add.recurrence = Some(recurrence);
+    }
+    quick_add.priority = priority.unwrap_or_default();
+    quick_add.content = content.join(" ");
+    quick_add
+}
+
+/// A date at the start of `words`, with how many words it took up.
+fn match_date(words: &[&str], today: Date) -> Option<(Date, usize)> {
+    let first = words[0].to_lowercase();
+    let second = words.get(1).map(|word| word.to_lowercase());
+
+    match (first.as_str(), second.as_deref()) {
+        ("today" | "tonight", _) => return Some((today, 1)),
+        ("tomorrow", _) => return Some((today.add_days(1), 1)),
+        ("next", Some("week")) => return Some((today.add_days(7), 2)),
+        ("in", Some(count)) => {
+            let count = count.parse::<i64>().ok()?;
+            let unit = words.get(2)?.to_lowercase();
+            let days = match unit.as_str() {
+                "day" | "days" => Some(count),
+                "week" | "weeks" => count.checked_mul(7),
+                _ => return None,
+            };
+            // Too far out to be a date is just text
+            return Some((today.checked_add_days(days?)?, 3));
+        }
+        ("on", Some(day)) => {
+            let weekday = Weekday::parse(day)?;
+            return Some((next_weekday(today, weekday), 2));
+        }
+        _ => {}
+    }
+
+    if let Some(weekday) = Weekday::parse(&first) {
+        return Some((next_weekday(today, weekday), 1));
+    }
+    Date::parse(&first).map(|date| (date, 1))
+}
+
+/// `weekday` this week if it hasn't passed yet, today included, or next week.
+fn next_weekday(today: Date, weekday: Weekday) -> Date {
+    today.add_days(today.weekday().days_until(weekday).into())
+}
+
+/// A time at the start of `words`, with how many words it took up.
+fn match_time(words: &[&str]) -> Option<(Time, usize)> {
+    if words[0].eq_ignore_ascii_case("at") {
+        let time = parse_time(words.get(1)?)?;
+        return Some((time, 2));
+    }
+    parse_time(words[0]).map(|time| (time, 1))
+}
+
+/// Parses `noon`, 24-hour `HH:MM`, or 12-hour `9am`, `9:30pm` and the like.
+fn parse_time(word: &str) -> Option<Time> {
+    let word = word.to_lowercase();
+    if word == "noon" {
+        return Time::new(12, 0);
+    }
+
+    let (clock, afternoon) = match word.strip_suffix("am") {
+        Some(clock) => (clock, false),
+        None => match word.strip_suffix("pm") {
+            Some(clock) => (clock, true),
+            // Without a suffix only `HH:MM` is a time, a bare number is not
+            None => return Time::parse(&word),
+        },
+    };
+
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) => (hour.parse::<u32>().ok()?, minute.parse().ok()?),
+        None => (clock.parse::<u32>().ok()?, 0),
+    };
+    if !(1..=12).contains(&hour) {
+        return None;
+    }
+    // 12am is midnight and 12pm is noon
+    let hour = hour % 12 + if afternoon { 12 } else { 0 };
+    Time::new(hour, minute)
+}
+
+fn parse_priority(word: &str) -> Option<Priority> {
+    let level = word.strip_prefix('!')?;
+    let priority = match level.to_lowercase().as_str() {
+        "" => Priority::Low,
+        "!" => Priority::Medium,
+        "!!" => Priority::High,
+        "!!!" => Priority::Urgent,
+        // "!none" is not a thing, leaving the priority out already means that
+        name => Priority::parse(name).filter(|priority| *priority != Priority::None)?,
+    };
+    Some(priority)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Tuesday, at 10:00.
+    fn parse_at_ten(text: &str) -> QuickAdd {
+        parse(text, date(2026, 10, 20), Time::new(10, 0).unwrap())
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> Date {
+        Date::new(year, month, day).unwrap()
+    }
+
+    fn time(hour: u32, minute: u32) -> Option<Time> {
+        Time::new(hour, minute)
+    }
+
+    #[test]
+    fn everything_at_once() {
+        assert_eq!(
+            parse_at_ten("Pay rent tomorrow 9am !high #home"),
+            QuickAdd {
+                content: "Pay rent".to_owned(),
+                due_date: Some(date(2026, 10, 21)),
+                due_time: time(9, 0),
+                priority: Priority::High,
+                tags: vec!["home".to_owned()],
+                recurrence: None,
+            }
+        );
+    }
+
+    #[test]
+    fn dates() {
+        for (text, due) in [
+            ("Call mom today", date(2026, 10, 20)),
+            ("Call mom tonight", date(2026, 10, 20)),
+            ("Call mom next week", date(2026, 10, 27)),
+            ("Call mom in 3 days", date(2026, 10, 23)),
+            ("Call mom in 1 day", date(2026, 10, 21)),
+            ("Call mom in 2 weeks", date(2026, 11, 3)),
+            ("Call mom friday", date(2026, 10, 23)),
+            ("Call mom on Fri", date(2026, 10, 23)),
+            // Today's weekday is today, not next week
+            ("Call mom tuesday", date(2026, 10, 20)),
+            ("Call mom monday", date(2026, 10, 26)),
+            ("Call mom 2027-01-31", date(2027, 1, 31)),
+        ] {
+            let quick_add = parse_at_ten(text);
+            assert_eq!(quick_add.due_date, Some(due), "{text}");
+            assert_eq!(quick_add.content, "Call mom", "{text}");
+        }
+    }
+
+    #[test]
+    fn times() {
+        for (text, due_time) in [
+            ("Standup 11am", time(11, 0)),
+            ("Standup at 9:30pm", time(21, 30)),
+            ("Standup 21:00", time(21, 0)),
+            ("Standup noon", time(12, 0)),
+            ("Standup 12pm", time(12, 0)),
+            ("Standup 12am", time(0, 0)),
+        ] {
+            let quick_add = parse_at_ten(text);
+            assert_eq!(quick_add.due_time, due_time, "{text}");
+            assert_eq!(quick_add.content, "Standup", "{text}");
+        }
+    }
+
+    #[test]
+    fn time_without_date_is_the_next_one() {
+        assert_eq!(parse_at_ten("Lunch 1pm").due_date, Some(date(2026, 10, 20)));
+        assert_eq!(parse_at_ten("Gym 9am").due_date, Some(date(2026, 10, 21)));
+        assert_eq!(
+            parse_at_ten("Gym friday 9am").due_date,
+            Some(date(2026, 10, 23))
+        );
+    }
+
+    #[test]
+    fn priorities() {
+        for (text, priority) in [
+            ("Fix bug !", Priority::Low),
+            ("Fix bug !!", Priority::Medium),
+            ("Fix bug !!!", Priority::High),
+            ("Fix bug !!!!", Priority::Urgent),
+            ("Fix bug !low", Priority::Low),
+            ("Fix bug !Medium", Priority::Medium),
+            ("Fix bug !urgent", Priority::Urgent),
+        ] {
+            let quick_add = parse_at_ten(text);
+            assert_eq!(quick_add.priority, priority, "{text}");
+            assert_eq!(quick_add.content, "Fix bug", "{text}");
+        }
+
+        let quick_add = parse_at_ten("Fix bug !high !low");
+        assert_eq!(quick_add.priority, Priority::High);
+        assert_eq!(quick_add.content, "Fix bug !low");
+    }
+
+    #[test]
+    fn tags() {
+        let quick_add = parse_at_ten("#Work Write report #q3-plan #work");
+        assert_eq!(quick_add.tags, ["work", "q3-plan"]);
+        assert_eq!(quick_add.content, "Write report");
+    }
+
+    #[test]
+    fn recurrence() {
+        let quick_add = parse_at_ten("Water plants daily");
+        assert_eq!(
+            quick_add.recurrence,
+            Some(Recurrence::Daily { interval: 1 })
+        );
+        assert_eq!(quick_add.due_date, Some(date(2026, 10, 20)));
+        assert_eq!(quick_add.content, "Water plants");
+    }
+
+    #[test]
+    fn other_text_is_untouched() {
+        for text in [
+            "Read chapter 9 of the book",
+            "Plan trip in the spring",
+            "Buy 2 apples",
+            "Email bob@example.com about C# and F#",
+            "Meet at the cafe",
+            "Train at 13pm",
+            "Answer !none and wow!",
+            "Note # is not a tag",
+            "Pick up in 3 months",
+            "Book 2026-02-30",
+        ] {
+            let quick_add = parse_at_ten(text);
+            assert_eq!(quick_add.content, text);
+            assert_eq!(quick_add.due_date, None, "{text}");
+            assert_eq!(quick_add.due_time, None, "{text}");
+            assert_eq!(quick_add.priority, Priority::None, "{text}");
+            assert!(quick_add.tags.is_empty(), "{text}");
+        }
+    }
+
+    #[test]
+    fn only_the_first_date_is_taken() {
+        let quick_add = parse_at_ten("Move today tomorrow");
+        assert_eq!(quick_add.due_date, Some(date(2026, 10, 20)));
+        assert_eq!(quick_add.content, "Move tomorrow");
+    }
+
+    #[test]
+    fn far_off_dates_stay_text() {
+        for text in [
+            "Retire in 9999999999999999999 weeks",
+            "Retire in 999999999999999999 weeks",
+            "Retire in 9999999999999 days",
+            "Retire in -9999999999999 days",
+        ] {
+            let quick_add = parse_at_ten(text);
+            assert_eq!(quick_add.due_date, None, "{text}");
+            assert_eq!(quick_add.content, text);
+        }
+    }
+}
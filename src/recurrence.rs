@@ -0,0 +1,531 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::date::{days_in_month, Date, Weekday};
+
+/// The largest interval a rule is parsed with. Larger intervals in the data
+/// file are kept, but clamped to it when the next due date is worked out.
+pub const MAX_INTERVAL: u32 = 1000;
+
+/// When a task comes back after being completed. Only dates are involved, so
+/// a due time stays the same wall-clock time across DST changes.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Recurrence {
+    Daily {
+        interval: u32,
+    },
+    /// On each of `weekdays` in every `interval`-th week. Without weekdays,
+    /// on the weekday of the current due date.
+    Weekly {
+        interval: u32,
+        weekdays: Vec<Weekday>,
+    },
+    /// On `day` of every `interval`-th month, or its last day if it is shorter.
+    Monthly {
+        interval: u32,
+        day: u32,
+    },
+    /// On `month`/`day` every `interval`-th year, on Feb 28 for Feb 29 in
+    /// common years.
+    Yearly {
+        interval: u32,
+        month: u32,
+        day: u32,
+    },
+    /// `days` days after the task was last completed, whatever its due date was.
+    AfterCompletion {
+        days: u32,
+    },
+}
+
+impl Recurrence {
+    /// The due date that follows `due` for a task completed on `completed_on`.
+    /// It is always after `due`, even if that is still in the past. `None`
+    /// past the last year a `Date` holds.
+    pub fn next_due(&self, due: Date, completed_on: Date) -> Option<Date> {
+        let clamp = |interval: u32| interval.clamp(1, MAX_INTERVAL);
+        match self {
+            Recurrence::Daily { interval } => due.checked_add_days(clamp(*interval).into()),
+            Recurrence::Weekly { interval, weekdays } => {
+                next_weekly(due, clamp(*interval), weekdays)
+            }
+            Recurrence::Monthly { interval, day } => {
+                let (year, month) = add_months(due.year(), due.month(), clamp(*interval))?;
+                Some(clamped_date(year, month, *day))
+            }
+            Recurrence::Yearly {
+                interval,
+                month,
+                day,
+            } => {
+                let year = due.year().checked_add_unsigned(clamp(*interval))?;
+                Some(clamped_date(year, *month, *day))
+            }
+            Recurrence::AfterCompletion { days } => {
+                completed_on.checked_add_days(clamp(*days).into())
+            }
+        }
+    }
+
+    /// The first day on or after `today` the rule falls on, for a task that
+    /// has no due date yet.
+    pub fn first_due(&self, today: Date) -> Date {
+        match self {
+            Recurrence::Weekly { weekdays, .. } => weekdays
+                .iter()
+                .map(|&weekday| today.add_days(today.weekday().days_until(weekday).into()))
+                .min()
+                .unwrap_or(today),
+            Recurrence::Monthly { day, .. } => {
+                let this_month = clamped_date(today.year(), today.month(), *day);
+                if this_month >= today {
+                    this_month
+                } else {
+                    let (year, month) =
+                        add_months(today.year(), today.month(), 1).expect("Today is in range");
+                    clamped_date(year, month, *day)
+                }
+            }
+            Recurrence::Yearly { month, day, .. } => {
+                let this_year = clamped_date(today.year(), *month, *day);
+                if this_year >= today {
+                    this_year
+                } else {
+                    clamped_date(today.year() + 1, *month, *day)
+                }
+            }
+            Recurrence::Daily { .. } | Recurrence::AfterCompletion { .. } => today,
+        }
+    }
+}
+
+fn next_weekly(due: Date, interval: u32, weekdays: &[Weekday]) -> Option<Date> {
+    let week_start = |date: Date| date.add_days(-i64::from(date.weekday().number_from_monday()));
+    let first_week = week_start(due);
+    let on_weekday = |date: Date| {
+        if weekdays.is_empty() {
+            date.weekday() == due.weekday()
+        } else {
+            weekdays.contains(&date.weekday())
+        }
+    };
+
+    // The answer is at most one skipped stretch of weeks plus a week away
+    for days in 1..=7 * i64::from(interval) + 7 {
+        let date = due.checked_add_days(days)?;
+        let weeks = week_start(date).days_since(first_week) / 7;
+        if weeks % i64::from(interval) == 0 && on_weekday(date) {
+            return Some(date);
+        }
+    }
+    unreachable!("Every week has each weekday")
+}
+
+fn add_months(year: i32, month: u32, months: u32) -> Option<(i32, u32)> {
+    let index = i64::from(year) * 12 + i64::from(month - 1) + i64::from(months);
+    let year = i32::try_from(index.div_euclid(12)).ok()?;
+    Some((year, index.rem_euclid(12) as u32 + 1))
+}
+
+fn clamped_date(year: i32, month: u32, day: u32) -> Date {
+    let day = day.clamp(1, days_in_month(year, month));
+    Date::new(year, month, day).expect("Clamped date is valid")
+}
+
+/// The short form shown to the user and understood by the quick-add entry,
+/// like "every 2 weeks on mon,fri" or "every 3 days after completion".
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let every = |f: &mut fmt::Formatter<'_>, interval: u32, unit: &str| match interval {
+            0 | 1 => write!(f, "every {unit}"),
+            n => write!(f, "every {n} {unit}s"),
+        };
+
+        match self {
+            Recurrence::Daily { interval } => every(f, *interval, "day"),
+            Recurrence::Weekly { interval, weekdays } => {
+                every(f, *interval, "week")?;
+                if !weekdays.is_empty() {
+                    let names = weekdays
+                        .iter()
+                        .map(|weekday| weekday.short_name())
+                        .collect::<Vec<_>>();
+                    write!(f, " on {}", names.join(","))?;
+                }
+                Ok(())
+            }
+            Recurrence::Monthly { interval, day } => {
+                every(f, *interval, "month")?;
+                write!(f, " on day {day}")
+            }
+            Recurrence::Yearly {
+                interval,
+                month,
+                day,
+            } => {
+                every(f, *interval, "year")?;
+                write!(f, " on {month:02}-{day:02}")
+            }
+            Recurrence::AfterCompletion { days } => {
+                every(f, *days, "day")?;
+                write!(f, " after completion")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseRecurrenceError;
+
+impl fmt::Display for ParseRecurrenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "not a recurrence rule")
+    }
+}
+
+impl std::error::Error for ParseRecurrenceError {}
+
+/// Parses the form written by `Display`. Anchors left out, like the day of a
+/// monthly rule, default to Jan 1, use `parse` to take them from a due date
+/// instead.
+impl FromStr for Recurrence {
+    type Err = ParseRecurrenceError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let anchor = Date::new(1970, 1, 1).expect("Valid date");
+        parse(text, anchor).ok_or(ParseRecurrenceError)
+    }
+}
+
+/// Parses a whole rule, taking anchors that are left out from `anchor`.
+pub fn parse(text: &str, anchor: Date) -> Option<Recurrence> {
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    match parse_words(&words, anchor)? {
+        (recurrence, consumed) if consumed == words.len() => Some(recurrence),
+        _ => None,
+    }
+}
+
+/// Parses a rule at the start of `words`, like "every month" or "weekly",
+/// returning it with how many words it took up. Anchors that are left out
+/// are taken from `anchor`, the task's due date.
+pub fn parse_words(words: &[&str], anchor: Date) -> Option<(Recurrence, usize)> {
+    let first = words.first()?.to_lowercase();
+    let single = match first.as_str() {
+        "daily" => Some((Unit::Day, 1)),
+        "weekly" => Some((Unit::Week, 1)),
+        "monthly" => Some((Unit::Month, 1)),
+        "yearly" | "annually" => Some((Unit::Year, 1)),
+        "every" => None,
+        _ => return None,
+    };
+
+    let (unit, interval, mut consumed) = match single {
+        Some((unit, interval)) => (unit, interval, 1),
+        None => {
+            let second = words.get(1)?.to_lowercase();
+            // "every monday" and "every mon,fri"
+            if let Some(weekdays) = parse_weekdays(&second) {
+                let recurrence = Recurrence::Weekly {
+                    interval: 1,
+                    weekdays,
+                };
+                return Some((recurrence, 2));
+            }
+            match second.parse::<u32>() {
+                Ok(interval) if (1..=MAX_INTERVAL).contains(&interval) => {
+                    let unit = Unit::parse(&words.get(2)?.to_lowercase())?;
+                    (unit, interval, 3)
+                }
+                Ok(_) => return None,
+                Err(_) => (Unit::parse(&second)?, 1, 2),
+            }
+        }
+    };
+
+    let rest = words[consumed..]
+        .iter()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>();
+    let rest = rest.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let recurrence = match unit {
+        Unit::Day => match rest.as_slice() {
+            ["after", "completion" | "done", ..] => {
+                consumed += 2;
+                Recurrence::AfterCompletion { days: interval }
+            }
+            _ => Recurrence::Daily { interval },
+        },
+        Unit::Week => {
+            let weekdays = match rest.as_slice() {
+                ["on", days, ..] => parse_weekdays(days),
+                _ => None,
+            };
+            if weekdays.is_some() {
+                consumed += 2;
+            }
+            Recurrence::Weekly {
+                interval,
+                weekdays: weekdays.unwrap_or_default(),
+            }
+        }
+        Unit::Month => {
+            let day = match rest.as_slice() {
+                ["on", "day", day, ..] => day.parse().ok().filter(|day| (1..=31).contains(day)),
+                _ => None,
+            };
+            if day.is_some() {
+                consumed += 3;
+            }
+            Recurrence::Monthly {
+                interval,
+                day: day.unwrap_or(anchor.day()),
+            }
+        }
+        Unit::Year => {
+            let month_day = match rest.as_slice() {
+                ["on", month_day, ..] => parse_month_day(month_day),
+                _ => None,
+            };
+            if month_day.is_some() {
+                consumed += 2;
+            }
+            let (month, day) = month_day.unwrap_or((anchor.month(), anchor.day()));
+            Recurrence::Yearly {
+                interval,
+                month,
+                day,
+            }
+        }
+    };
+
+    Some((recurrence, consumed))
+}
+
+enum Unit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Unit {
+    fn parse(word: &str) -> Option<Self> {
+        let unit = match word {
+            "day" | "days" => Unit::Day,
+            "week" | "weeks" => Unit::Week,
+            "month" | "months" => Unit::Month,
+            "year" | "years" => Unit::Year,
+            _ => return None,
+        };
+        Some(unit)
+    }
+}
+
+/// Parses comma separated day names, like "mon,fri".
+fn parse_weekdays(text: &str) -> Option<Vec<Weekday>> {
+    let mut weekdays = text
+        .split(',')
+        .map(Weekday::parse)
+        .collect::<Option<Vec<_>>>()?;
+    weekdays.sort();
+    weekdays.dedup();
+    Some(weekdays)
+}
+
+/// Parses `MM-DD`, allowing Feb 29.
+fn parse_month_day(text: &str) -> Option<(u32, u32)> {
+    let (month, day) = text.split_once('-')?;
+    let (month, day) = (month.parse().ok()?, day.parse().ok()?);
+    // 2000 was a leap year, so every real day of the year is valid in it
+    Date::new(2000, month, day)?;
+    Some((month, day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> Date {
+        Date::new(year, month, day).unwrap()
+    }
+
+    /// The next due date for a task completed on its due date.
+    fn next(recurrence: &Recurrence, due: Date) -> Date {
+        recurrence.next_due(due, due).unwrap()
+    }
+
+    #[test]
+    fn daily() {
+        let every_two_days = Recurrence::Daily { interval: 2 };
+        assert_eq!(next(&every_two_days, date(2026, 12, 31)), date(2027, 1, 2));
+    }
+
+    #[test]
+    fn monthly_clamps_to_month_end() {
+        let end_of_month = Recurrence::Monthly {
+            interval: 1,
+            day: 31,
+        };
+        assert_eq!(next(&end_of_month, date(2026, 1, 31)), date(2026, 2, 28));
+        assert_eq!(next(&end_of_month, date(2028, 1, 31)), date(2028, 2, 29));
+        // The day is kept, so a short month doesn't shorten the ones after it
+        assert_eq!(next(&end_of_month, date(2026, 2, 28)), date(2026, 3, 31));
+        assert_eq!(next(&end_of_month, date(2026, 3, 31)), date(2026, 4, 30));
+
+        let quarterly = Recurrence::Monthly {
+            interval: 3,
+            day: 30,
+        };
+        assert_eq!(next(&quarterly, date(2026, 11, 30)), date(2027, 2, 28));
+    }
+
+    #[test]
+    fn yearly_on_leap_day() {
+        let leap_day = Recurrence::Yearly {
+            interval: 1,
+            month: 2,
+            day: 29,
+        };
+        assert_eq!(next(&leap_day, date(2028, 2, 29)), date(2029, 2, 28));
+        assert_eq!(next(&leap_day, date(2031, 2, 28)), date(2032, 2, 29));
+        assert_eq!(leap_day.first_due(date(2026, 3, 1)), date(2027, 2, 28));
+    }
+
+    #[test]
+    fn weekly_on_several_weekdays() {
+        let mondays_and_fridays = Recurrence::Weekly {
+            interval: 1,
+            weekdays: vec![Weekday::Monday, Weekday::Friday],
+        };
+        // 2026-10-19 is a Monday
+        assert_eq!(
+            next(&mondays_and_fridays, date(2026, 10, 19)),
+            date(2026, 10, 23)
+        );
+        assert_eq!(
+            next(&mondays_and_fridays, date(2026, 10, 23)),
+            date(2026, 10, 26)
+        );
+        // Off-rule due dates go to the next day the rule has
+        assert_eq!(
+            next(&mondays_and_fridays, date(2026, 10, 20)),
+            date(2026, 10, 23)
+        );
+
+        let every_other_week = Recurrence::Weekly {
+            interval: 2,
+            weekdays: vec![Weekday::Monday, Weekday::Friday],
+        };
+        assert_eq!(
+            next(&every_other_week, date(2026, 10, 19)),
+            date(2026, 10, 23)
+        );
+        assert_eq!(
+            next(&every_other_week, date(2026, 10, 23)),
+            date(2026, 11, 2)
+        );
+
+        let same_weekday = Recurrence::Weekly {
+            interval: 1,
+            weekdays: Vec::new(),
+        };
+        assert_eq!(next(&same_weekday, date(2026, 10, 20)), date(2026, 10, 27));
+
+        assert_eq!(
+            mondays_and_fridays.first_due(date(2026, 10, 20)),
+            date(2026, 10, 23)
+        );
+    }
+
+    #[test]
+    fn after_completion() {
+        let three_days_later = Recurrence::AfterCompletion { days: 3 };
+        assert_eq!(
+            three_days_later.next_due(date(2026, 10, 1), date(2026, 10, 20)),
+            Some(date(2026, 10, 23))
+        );
+        // Completed early, it still counts from the completion
+        assert_eq!(
+            three_days_later.next_due(date(2026, 10, 30), date(2026, 10, 20)),
+            Some(date(2026, 10, 23))
+        );
+    }
+
+    #[test]
+    fn runs_out_of_dates() {
+        let last_year = date(i32::MAX, 6, 1);
+        for recurrence in [
+            Recurrence::Daily { interval: 1000 },
+            Recurrence::Monthly {
+                interval: 12,
+                day: 1,
+            },
+            Recurrence::Yearly {
+                interval: 1,
+                month: 6,
+                day: 1,
+            },
+            Recurrence::Weekly {
+                interval: u32::MAX,
+                weekdays: Vec::new(),
+            },
+        ] {
+            assert_eq!(
+                recurrence.next_due(last_year, last_year),
+                None,
+                "{recurrence}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_what_it_displays() {
+        let anchor = date(2026, 10, 20);
+        for text in [
+            "every day",
+            "every 3 days",
+            "every week on mon,fri",
+            "every 2 weeks",
+            "every month on day 31",
+            "every year on 02-29",
+            "every 3 days after completion",
+        ] {
+            let recurrence = parse(text, anchor).unwrap_or_else(|| panic!("{text}"));
+            assert_eq!(recurrence.to_string(), text);
+        }
+
+        assert_eq!(
+            parse("monthly", anchor),
+            Some(Recurrence::Monthly {
+                interval: 1,
+                day: 20
+            })
+        );
+        assert_eq!(
+            parse("every fri,mon", anchor),
+            Some(Recurrence::Weekly {
+                interval: 1,
+                weekdays: vec![Weekday::Monday, Weekday::Friday]
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_absurd_intervals() {
+        let anchor = date(2026, 10, 20);
+        assert!(parse("every 1000 weeks", anchor).is_some());
+        for text in [
+            "every 0 days",
+            "every 1001 days",
+            "every 4000000000 weeks",
+            "every 99999999999 years",
+        ] {
+            assert_eq!(parse(text, anchor), None, "{text}");
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+use adw::prelude::*;
+use glib::clone;
+use gtk::{gio, glib};
+
+use crate::utils::local_date_time;
+use crate::widgets::{CollectionObject, TaskObject};
+
+/// How long "Snooze" postpones a reminder.
+const SNOOZE_MINUTES: i32 = 10;
+
+/// Source of the current time and of one-shot timers. The scheduler only
+/// talks to the clock, so it can be driven without waiting in real time.
+pub trait Clock {
+    type Timer;
+
+    fn now(&self) -> glib::DateTime;
+    fn start_timer(&self, delay: Duration, callback: Box<dyn FnOnce()>) -> Self::Timer;
+    fn cancel_timer(&self, timer: Self::Timer);
+}
+
+/// The local wall clock, with timers on the default main context.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    type Timer = glib::SourceId;
+
+    fn now(&self) -> glib::DateTime {
+        glib::DateTime::now_local().expect("Could not get the local time")
+    }
+
+    fn start_timer(&self, delay: Duration, callback: Box<dyn FnOnce()>) -> Self::Timer {
+        glib::timeout_add_local_once(delay, callback)
+    }
+
+    fn cancel_timer(&self, timer: Self::Timer) {
+        timer.remove();
+    }
+}
+
+/// Calls `on_due` for every open task whose due time (or snooze) passes while
+/// the app is running.
+pub struct Reminders<C: Clock> {
+    collections: gio::ListStore,
+    clock: C,
+    on_due: Box<dyn Fn(&TaskObject)>,
+    last_check: RefCell<glib::DateTime>,
+    snoozed: RefCell<HashMap<String, glib::DateTime>>,
+    timer: RefCell<Option<C::Timer>>,
+}
+
+impl<C: Clock + 'static> Reminders<C> {
+    pub fn new(
+        collections: &gio::ListStore,
+        clock: C,
+        on_due: impl Fn(&TaskObject) + 'static,
+    ) -> Rc<Self> {
+        let reminders = Rc::new(Self {
+            collections: collections.clone(),
+            last_check: RefCell::new(clock.now()),
+            clock,
+            on_due: Box::new(on_due),
+            snoozed: RefCell::default(),
+            timer: RefCell::default(),
+        });
+        reminders.schedule_tick();
+        reminders
+    }
+
+    /// Reminds about the task with `id` again in a few minutes.
+    pub fn snooze(&self, id: &str) {
+        let until = self
+            .clock
+            .now()
+            .add_minutes(SNOOZE_MINUTES)
+            .expect("Snooze time out of range");
+        self.snoozed.borrow_mut().insert(id.to_owned(), until);
+    }
+
+    fn schedule_tick(self: &Rc<Self>) {
+        if let Some(timer) = self.timer.take() {
+            self.clock.cancel_timer(timer);
+        }
+
+        // Due times have minute precision, so waking up just after each
+        // minute boundary is enough and copes with suspend and clock changes
+        let seconds_left = 60.0 - self.clock.now().seconds();
+        let delay = Duration::from_secs_f64(seconds_left.clamp(0.001, 60.0));
+
+        let timer = self.clock.start_timer(
+            delay,
+            Box::new(clone!(
+                #[weak(rename_to = reminders)]
+                self,
+                move || {
+                    reminders.timer.take();
+                    reminders.tick();
+                }
+            )),
+        );
+        self.timer.replace(Some(timer));
+    }
+
+    fn tick(self: &Rc<Self>) {
+        let now = self.clock.now();
+        let last_check = self.last_check.replace(now.clone());
+        let came_due = |moment: &glib::DateTime| last_check < *moment && *moment <= now;
+
+        let due_tasks = self
+            .collections
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            // Subtasks have due times of their own
+            .flat_map(|collection| collection.tasks_with_parents())
+            .map(|(_, task)| task)
+            .filter(|task| !task.is_completed())
+            .filter(|task| {
+                let due_at = task
+                    .due()
+                    .and_then(|(date, time)| local_date_time(date, Some(time?)));
+                let snoozed_until = self.snoozed.borrow().get(&task.id()).cloned();
+                due_at.is_some_and(|due_at| came_due(&due_at))
+                    || snoozed_until.is_some_and(|until| came_due(&until))
+            })
+            .collect::<Vec<_>>();
+
+        self.snoozed.borrow_mut().retain(|_, until| *until > now);
+
+        for task in &due_tasks {
+            (self.on_due)(task);
+        }
+
+        self.schedule_tick();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    type PendingTimer = (u32, glib::DateTime, Box<dyn FnOnce()>);
+
+    /// Time only moves when a test says so, and timers fire as it passes them.
+    #[derive(Clone)]
+    struct FakeClock {
+        now: Rc<RefCell<glib::DateTime>>,
+        timers: Rc<RefCell<Vec<PendingTimer>>>,
+        next_timer: Rc<Cell<u32>>,
+    }
+
+    impl FakeClock {
+        fn new(now: glib::DateTime) -> Self {
+            Self {
+                now: Rc::new(RefCell::new(now)),
+                timers: Rc::default(),
+                next_timer: Rc::default(),
+            }
+        }
+
+        fn advance_to(&self, moment: &glib::DateTime) {
+            self.now.replace(moment.clone());
+            loop {
+                let due = {
+                    let mut timers = self.timers.borrow_mut();
+                    let position = timers.iter().position(|(_, at, _)| at <= moment);
+                    position.map(|position| timers.remove(position))
+                };
+                let Some((_, _, callback)) = due else {
+                    break;
+                };
+                callback();
+            }
+        }
+    }
+
+    impl Clock for FakeClock {
+        type Timer = u32;
+
+        fn now(&self) -> glib::DateTime {
+            self.now.borrow().clone()
+        }
+
+        fn start_timer(&self, delay: Duration, callback: Box<dyn FnOnce()>) -> u32 {
+            let at = self
+                .now()
+                .add_seconds(delay.as_secs_f64())
+                .expect("Timer out of range");
+            let id = self.next_timer.get();
+            self.next_timer.set(id + 1);
+            self.timers.borrow_mut().push((id, at, callback));
+            id
+        }
+
+        fn cancel_timer(&self, timer: u32) {
+            self.timers.borrow_mut().retain(|(id, _, _)| *id != timer);
+        }
+    }
+
+    fn at(hour: i32, minute: i32) -> glib::DateTime {
+        glib::DateTime::from_local(2026, 10, 20, hour, minute, 0.0).unwrap()
+    }
+
+    /// A task due at 10:00, with reminders started at 9:59:30 and every due
+    /// task's ID recorded.
+    struct Fixture {
+        clock: FakeClock,
+        task: TaskObject,
+        reminders: Rc<Reminders<FakeClock>>,
+        fired: Rc<RefCell<Vec<String>>>,
+    }
+
+    fn setup() -> Fixture {
+        let task = TaskObject::new(false, "Call the dentist".to_owned());
+        task.set_due_date(Some("2026-10-20"));
+        task.set_due_time(Some("10:00"));
+
+        let tasks = gio::ListStore::new::<TaskObject>();
+        tasks.append(&task);
+        let collections = gio::ListStore::new::<CollectionObject>();
+        collections.append(&CollectionObject::new("Health", tasks));
+
+        let clock = FakeClock::new(at(9, 59).add_seconds(30.0).unwrap());
+        let fired = Rc::new(RefCell::new(Vec::new()));
+        let reminders = Reminders::new(
+            &collections,
+            clock.clone(),
+            clone!(
+                #[strong]
+                fired,
+                move |task| fired.borrow_mut().push(task.id())
+            ),
+        );
+        Fixture {
+            clock,
+            task,
+            reminders,
+            fired,
+        }
+    }
+
+    #[test]
+    fn due_task_fires_once() {
+        // The reminders stop once dropped, so they are kept until the end
+        let Fixture {
+            clock,
+            task,
+            reminders: _reminders,
+            fired,
+        } = setup();
+
+        clock.advance_to(&at(9, 59).add_seconds(59.0).unwrap());
+        assert!(fired.borrow().is_empty());
+
+        clock.advance_to(&at(10, 0));
+        assert_eq!(*fired.borrow(), [task.id()]);
+
+        clock.advance_to(&at(10, 1));
+        clock.advance_to(&at(11, 0));
+        assert_eq!(fired.borrow().len(), 1);
+    }
+
+    #[test]
+    fn snoozed_task_fires_again() {
+        let Fixture {
+            clock,
+            task,
+            reminders,
+            fired,
+        } = setup();
+
+        clock.advance_to(&at(10, 0));
+        reminders.snooze(&task.id());
+
+        clock.advance_to(&at(10, SNOOZE_MINUTES - 1));
+        assert_eq!(fired.borrow().len(), 1);
+
+        clock.advance_to(&at(10, SNOOZE_MINUTES));
+        assert_eq!(*fired.borrow(), [task.id(), task.id()]);
+
+        clock.advance_to(&at(10, 59));
+        assert_eq!(fired.borrow().len(), 2);
+    }
+
+    #[test]
+    fn completed_task_does_not_fire() {
+        // The reminders stop once dropped, so they are kept until the end
+        let Fixture {
+            clock,
+            task,
+            reminders: _reminders,
+            fired,
+        } = setup();
+
+        task.set_completed(true);
+        clock.advance_to(&at(10, 0));
+        clock.advance_to(&at(10, 30));
+        assert!(fired.borrow().is_empty());
+    }
+
+    #[test]
+    fn due_subtask_fires() {
+        let Fixture {
+            clock,
+            task,
+            reminders: _reminders,
+            fired,
+        } = setup();
+        let subtask = TaskObject::new(false, "Find the insurance card".to_owned());
+        subtask.set_due_date(Some("2026-10-20"));
+        subtask.set_due_time(Some("10:05"));
+        let nested = TaskObject::new(false, "Check the expiry date".to_owned());
+        nested.set_due_date(Some("2026-10-20"));
+        nested.set_due_time(Some("10:05"));
+        subtask.subtasks().append(&nested);
+        task.subtasks().append(&subtask);
+
+        clock.advance_to(&at(10, 0));
+        assert_eq!(*fired.borrow(), [task.id()]);
+
+        clock.advance_to(&at(10, 5));
+        assert_eq!(*fired.borrow(), [task.id(), subtask.id(), nested.id()]);
+    }
+}
@@ -0,0 +1,10 @@
+/// Parses a `#tag` word into its lowercase name. A tag is `#` followed by
+/// letters, digits, `-` or `_`, like `#work` or `#q3-plan`.
+pub fn parse(word: &str) -> Option<String> {
+    let name = word.strip_prefix('#')?;
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    valid.then(|| name.to_lowercase())
+}
@@ -0,0 +1,691 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::date::{Date, Time};
+use crate::recurrence::{Recurrence, MAX_INTERVAL};
+use crate::tags;
+use crate::widgets::{CollectionData, Priority, TaskData};
+
+/// Writes every task of `collections` as a line in the form `parse` reads,
+/// subtasks after their task.
+/// `local_date` gives the local day of a Unix time, for the creation and
+/// completion dates, which are left out where it gives `None`.
+pub fn export(collections: &[CollectionData], local_date: impl Fn(i64) -> Option<Date>) -> String {
+    let mut lines = Vec::new();
+    let mut next_id = 1;
+    for collection in collections {
+        let project = project_name(&collection.title);
+        for task in &collection.tasks_data {
+            export_task(&mut lines, task, &project, None, &mut next_id, &local_date);
+        }
+    }
+    lines.iter().map(|line| format!("{line}\n")).collect()
+}
+
+fn export_task(
+    lines: &mut Vec<String>,
+    task: &TaskData,
+    project: &str,
+    parent: Option<u32>,
+    next_id: &mut u32,
+    local_date: &impl Fn(i64) -> Option<Date>,
+) {
+    let mut words = Vec::new();
+    let created = (task.created_at != 0)
+        .then(|| local_date(task.created_at))
+        .flatten();
+    if task.completed {
+        words.push("x".to_owned());
+        // The creation date can only follow a completion date
+        let completed = (task.modified_at != 0)
+            .then(|| local_date(task.modified_at))
+            .flatten();
+        if let Some(completed) = completed {
+            words.push(completed.to_string());
+            words.extend(created.map(|date| date.to_string()));
+        }
+    } else {
+        words.extend(priority_letter(task.priority).map(|letter| format!("({letter})")));
+        words.extend(created.map(|date| date.to_string()));
+    }
+
+    words.extend(
+        task.content
+            .split_whitespace()
+            .enumerate()
+            .map(|(index, word)| escape(word, index == 0)),
+    );
+    words.extend(task.tags.iter().map(|tag| format!("@{tag}")));
+    if !project.is_empty() {
+        words.push(format!("+{project}"));
+    }
+    if let Some(date) = &task.due_date {
+        words.push(format!("due:{date}"));
+        words.extend(task.due_time.iter().map(|time| format!("time:{time}")));
+    }
+    let due = task.due_date.as_deref().and_then(Date::parse);
+    if let Some(rule) = task
+        .recurrence
+        .as_ref()
+        .and_then(|rule| export_rule(rule, due))
+    {
+        words.push(format!("rec:{rule}"));
+    }
+    if task.completed {
+        words.extend(priority_letter(task.priority).map(|letter| format!("pri:{letter}")));
+    }
+
+    let id = (!task.subtasks.is_empty()).then(|| {
+        let id = *next_id;
+        *next_id += 1;
+        id
+    });
+    words.extend(id.map(|id| format!("id:{id}")));
+    words.extend(parent.map(|parent| format!("p:{parent}")));
+    lines.push(words.join(" "));
+
+    for subtask in &task.subtasks {
+        export_task(lines, subtask, project, id, next_id, local_date);
+    }
+}
+
+/// Collection titles can have spaces, projects can't.
+fn project_name(title: &str) -> String {
+    title.split_whitespace().collect::<Vec<_>>().join("-")
+}
+
+/// Whether a collection called `title` is the one for `project`.
+pub fn is_project(title: &str, project: &str) -> bool {
+    project_name(title).eq_ignore_ascii_case(project)
+}
+
+/// The extras `parse` understands, as in `key:value`.
+const EXTRAS: [&str; 6] = ["due", "time", "rec", "pri", "id", "p"];
+
+/// `word` with a `\` in front if it would be read as anything but content.
+/// `first` is for the first word, which could also be taken for the
+/// completion mark, a priority or the creation date.
+fn escape(word: &str, first: bool) -> String {
+    if needs_escape(word, first) {
+        format!("\\{word}")
+    } else {
+        word.to_owned()
+    }
+}
+
+fn needs_escape(word: &str, first: bool) -> bool {
+    // So escaped words that are really content keep their backslash
+    if let Some(word) = word.strip_prefix('\\') {
+        return needs_escape(word, first);
+    }
+    let header =
+        first && (word == "x" || Date::parse(word).is_some() || parse_priority(word).is_some());
+    let marked = (word.starts_with('+') || word.starts_with('@')) && word.len() > 1;
+    let extra = word
+        .split_once(':')
+        .is_some_and(|(key, _)| EXTRAS.contains(&key));
+    header || marked || extra
+}
+
+/// A priority like "(A)".
+fn parse_priority(word: &str) -> Option<Priority> {
+    let letter = word.strip_prefix('(')?.strip_suffix(')')?;
+    let mut chars = letter.chars();
+    let letter = chars.next().filter(|_| chars.next().is_none())?;
+    letter_priority(letter)
+}
+
+fn priority_letter(priority: Priority) -> Option<char> {
+    match priority {
+        Priority::None => None,
+        Priority::Low => Some('D'),
+        Priority::Medium => Some('C'),
+        Priority::High => Some('B'),
+        Priority::Urgent => Some('A'),
+    }
+}
+
+fn letter_priority(letter: char) -> Option<Priority> {
+    let priority = match letter {
+        'A' => Priority::Urgent,
+        'B' => Priority::High,
+        'C' => Priority::Medium,
+        'D'..='Z' => Priority::Low,
+        _ => return None,
+    };
+    Some(priority)
+}
+
+fn export_rule(recurrence: &Recurrence, due: Option<Date>) -> Option<String> {
+    let rule = match recurrence {
+        Recurrence::Daily { interval } => format!("+{}d", (*interval).max(1)),
+        Recurrence::Weekly { interval, weekdays } => {
+            let on_due_weekday = match weekdays.as_slice() {
+                [] => true,
+                [weekday] => due.is_some_and(|due| due.weekday() == *weekday),
+                _ => false,
+            };
+            if !on_due_weekday {
+                return None;
+            }
+            format!("+{}w", (*interval).max(1))
+        }
+        Recurrence::Monthly { interval, .. } => format!("+{}m", (*interval).max(1)),
+        Recurrence::Yearly { interval, .. } => format!("+{}y", (*interval).max(1)),
+        Recurrence::AfterCompletion { days } if days % 7 == 0 && *days > 0 => {
+            format!("{}w", days / 7)
+        }
+        Recurrence::AfterCompletion { days } => format!("{}d", (*days).max(1)),
+    };
+    Some(rule)
+}
+
+/// Reads `rec:` rules, taking the day of monthly and yearly ones from `anchor`.
+/// Intervals beyond what the app itself takes are left out.
+fn import_rule(rule: &str, anchor: Option<Date>) -> Option<Recurrence> {
+    let (strict, rule) = match rule.strip_prefix('+') {
+        Some(rule) => (true, rule),
+        None => (false, rule),
+    };
+    let unit = rule.chars().last()?;
+    let count = match &rule[..rule.len() - unit.len_utf8()] {
+        "" => 1,
+        count => count
+            .parse()
+            .ok()
+            .filter(|count: &u32| (1..=MAX_INTERVAL).contains(count))?,
+    };
+    let anchor = anchor.unwrap_or_else(|| Date::new(1970, 1, 1).expect("Valid date"));
+
+    let recurrence = match (unit, strict) {
+        ('d', true) => Recurrence::Daily { interval: count },
+        ('w', true) => Recurrence::Weekly {
+            interval: count,
+            weekdays: Vec::new(),
+        },
+        ('d', false) => Recurrence::AfterCompletion { days: count },
+        ('w', false) => Recurrence::AfterCompletion {
+            days: Some(count * 7).filter(|&days| days <= MAX_INTERVAL)?,
+        },
+        // Months and years vary in length, so those go by the due date either way
+        ('m', _) => Recurrence::Monthly {
+            interval: count,
+            day: anchor.day(),
+        },
+        ('y', _) => Recurrence::Yearly {
+            interval: count,
+            month: anchor.month(),
+            day: anchor.day(),
+        },
+        _ => return None,
+    };
+    Some(recurrence)
+}
+
+/// Reads the lines of a todo.txt file into collections, one for each
+/// project in the order they first show up. Tasks without a project end up
+/// in a collection with an empty title. IDs are left empty.
+///
+/// Lines look like "x 2026-10-18 2026-10-01 Pay rent @home +Household due:2026-11-01",
+/// see https://github.com/todotxt/todo.txt for the format. Besides the
+/// `+project` of the collection and `@context`s for tags, these extras are
+/// understood:
+///
+/// - `due:YYYY-MM-DD`, with `time:HH:MM` for a due time
+/// - `rec:` like `+2w` for rules that go by the due date, and like `3d`
+///   without the `+` for rules that go by the completion
+/// - `pri:A` for the priority of completed tasks, which lose their `(A)`
+/// - `id:` on a task and `p:` with the same value on its subtasks, like
+///   topydo writes them
+///
+/// Anything else, including other extras and any further `+project`s, stays
+/// in the task's content so it survives a round trip. `export` puts a `\` in
+/// front of words of the content that would be read as any of the above,
+/// which is taken off here again. Notes, runs of whitespace in the content
+/// and weekly rules on several weekdays have no todo.txt counterpart and are
+/// left out.
+pub fn parse(text: &str) -> Vec<CollectionData> {
+    let lines = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(Line::parse)
+        .collect::<Vec<_>>();
+
+    // Only links between tasks in the file are taken apart, others stay text
+    let ids = lines
+        .iter()
+        .filter_map(|line| line.id.as_deref())
+        .collect::<HashSet<_>>();
+    let parents = lines
+        .iter()
+        .filter_map(|line| line.parent.as_deref())
+        .filter(|parent| ids.contains(parent))
+        .collect::<HashSet<_>>();
+
+    let mut tasks = Vec::new();
+    let mut children = HashMap::<String, Vec<usize>>::new();
+    let mut roots = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let mut task = line.task.clone();
+        let id = line.id.as_deref().filter(|id| parents.contains(id));
+        if let (Some(id), None) = (&line.id, id) {
+            push_word(&mut task.content, &format!("id:{id}"));
+        }
+        match line.parent.as_deref() {
+            Some(parent) if parents.contains(parent) && line.id.as_deref() != Some(parent) => {
+                children.entry(parent.to_owned()).or_default().push(index);
+            }
+            Some(parent) => {
+                push_word(&mut task.content, &format!("p:{parent}"));
+                roots.push(index);
+            }
+            None => roots.push(index),
+        }
+        tasks.push(Some((task, id.map(str::to_owned))));
+    }
+
+    let mut collections = Vec::<CollectionData>::new();
+    for index in roots.into_iter().chain(0..lines.len()) {
+        let Some(task) = take_with_subtasks(&mut tasks, &children, index) else {
+            continue;
+        };
+        let title = lines[index].project.clone().unwrap_or_default();
+        match collections
+            .iter_mut()
+            .find(|collection| collection.title == title)
+        {
+            Some(collection) => collection.tasks_data.push(task),
+            None => collections.push(CollectionData {
+                id: String::new(),
+                title,
+                tasks_data: vec![task],
+            }),
+        }
+    }
+    collections
+}
+
+/// Takes the task at `index` out of `tasks`, with the subtasks under it that
+/// are not taken yet.
+fn take_with_subtasks(
+    tasks: &mut [Option<(TaskData, Option<String>)>],
+    children: &HashMap<String, Vec<usize>>,
+    index: usize,
+) -> Option<TaskData> {
+    let (mut task, id) = tasks[index].take()?;
+    let subtasks = id.and_then(|id| children.get(&id));
+    for &child in subtasks.into_iter().flatten() {
+        if let Some(subtask) = take_with_subtasks(tasks, children, child) {
+            task.subtasks.push(subtask);
+        }
+    }
+    Some(task)
+}
+
+/// One line, before tasks are put under their parents.
+struct Line {
+    task: TaskData,
+    /// The last `+project`, earlier ones stay in the content
+    project: Option<String>,
+    id: Option<String>,
+    parent: Option<String>,
+}
+
+impl Line {
+    fn parse(line: &str) -> Self {
+        let mut task = TaskData::default();
+        let mut words = line.split_whitespace().peekable();
+
+        let mut created = None;
+        if words.peek() == Some(&"x") {
+            words.next();
+            task.completed = true;
+            // A completion date, then maybe a creation date. Completing is
+            // the last edit as far as anyone knows
+            if let Some(completed) = words.next_if(|word| Date::parse(word).is_some()) {
+                task.modified_at = Date::parse(completed).map_or(0, unix_noon);
+                created = words.next_if(|word| Date::parse(word).is_some());
+            }
+        } else {
+            let priority = words.peek().and_then(|word| parse_priority(word));
+            if let Some(priority) = priority {
+                words.next();
+                task.priority = priority;
+            }
+            created = words.next_if(|word| Date::parse(word).is_some());
+        }
+        task.created_at = created.and_then(Date::parse).map_or(0, unix_noon);
+
+        let mut project = None;
+        let mut id = None;
+        let mut parent = None;
+        let mut rule = None;
+        let mut content = Vec::new();
+        for (index, word) in words.enumerate() {
+            if let Some(escaped) = word
+                .strip_prefix('\\')
+                .filter(|escaped| needs_escape(escaped, index == 0))
+            {
+                content.push(escaped.to_owned());
+                continue;
+            }
+            if let Some(name) = word.strip_prefix('+').filter(|name| !name.is_empty()) {
+                // Only the last one is the collection, put back the one before
+                content.extend(project.replace(name).map(|name| format!("+{name}")));
+                continue;
+            }
+            if let Some(tag) = word
+                .strip_prefix('@')
+                .and_then(|name| tags::parse(&format!("#{name}")))
+            {
+                if !task.tags.contains(&tag) {
+                    task.tags.push(tag);
+                }
+                continue;
+            }
+
+            let understood = match word.split_once(':') {
+                Some(("due", date)) if Date::parse(date).is_some() => {
+                    task.due_date = Some(date.to_owned());
+                    true
+                }
+                Some(("time", time)) if Time::parse(time).is_some() => {
+                    task.due_time = Some(time.to_owned());
+                    true
+                }
+                Some(("rec", value)) if import_rule(value, None).is_some() => {
+                    rule = Some(value);
+                    true
+                }
+                Some(("pri", letter)) if task.completed => {
+                    let mut chars = letter.chars();
+                    let priority = chars
+                        .next()
+                        .filter(|_| chars.next().is_none())
+                        .and_then(letter_priority);
+                    task.priority = priority.unwrap_or(task.priority);
+                    priority.is_some()
+                }
+                Some(("id", value)) if !value.is_empty() => {
+                    id = Some(value.to_owned());
+                    true
+                }
+                Some(("p", value)) if !value.is_empty() => {
+                    parent = Some(value.to_owned());
+                    true
+                }
+                _ => false,
+            };
+            if !understood {
+                content.push(word.to_owned());
+            }
+        }
+        // A time is only meaningful with a date
+        if task.due_date.is_none() {
+            content.extend(task.due_time.take().map(|time| format!("time:{time}")));
+        }
+        task.content = content.join(" ");
+
+        let anchor = task
+            .due_date
+            .as_deref()
+            .and_then(Date::parse)
+            .or(created.and_then(Date::parse));
+        task.recurrence = rule.and_then(|rule| import_rule(rule, anchor));
+
+        Self {
+            task,
+            project: project.map(str::to_owned),
+            id,
+            parent,
+        }
+    }
+}
+
+/// Noon in UTC on `date`, which is on the same day in almost every time zone.
+fn unix_noon(date: Date) -> i64 {
+    let epoch = Date::new(1970, 1, 1).expect("Valid date");
+    date.days_since(epoch) * 86_400 + 43_200
+}
+
+fn push_word(text: &mut String, word: &str) {
+    if !text.is_empty() {
+        text.push(' ');
+    }
+    text.push_str(word);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Dates in UTC, which `unix_noon` is on the same day of.
+    fn utc_date(unix: i64) -> Option<Date> {
+        Date::new(1970, 1, 1)?.checked_add_days(unix.div_euclid(86_400))
+    }
+
+    /// Collections compared by everything they store.
+    fn json(collections: &[CollectionData]) -> serde_json::Value {
+        serde_json::to_value(collections).unwrap()
+    }
+
+    /// Parses `text`, then checks that writing it out and reading it back
+    /// in changes nothing.
+    fn round_trip(text: &str) -> Vec<CollectionData> {
+        let collections = parse(text);
+        let exported = export(&collections, utc_date);
+        assert_eq!(
+            json(&parse(&exported)),
+            json(&collections),
+            "{text}\n{exported}"
+        );
+        collections
+    }
+
+    fn only_task(text: &str) -> TaskData {
+        let mut collections = round_trip(text);
+        assert_eq!(collections.len(), 1);
+        assert_eq!(collections[0].tasks_data.len(), 1);
+        collections.remove(0).tasks_data.remove(0)
+    }
+
+    fn date(text: &str) -> i64 {
+        unix_noon(Date::parse(text).unwrap())
+    }
+
+    /// The examples of https://github.com/todotxt/todo.txt
+    #[test]
+    fn examples_of_the_format() {
+        let task = only_task("(A) Thank Mom for the meatballs @phone");
+        assert_eq!(task.priority, Priority::Urgent);
+        assert_eq!(task.content, "Thank Mom for the meatballs");
+        assert_eq!(task.tags, ["phone"]);
+
+        let collections = round_trip(
+            "(B) Schedule Goodwill pickup +GarageSale @phone\n\
+             Post signs around the neighborhood +GarageSale\n\
+             @GroceryStore Eskimo pies\n",
+        );
+        assert_eq!(collections[0].title, "GarageSale");
+        assert_eq!(collections[0].tasks_data.len(), 2);
+        assert_eq!(collections[1].title, "");
+        assert_eq!(collections[1].tasks_data[0].content, "Eskimo pies");
+
+        for text in [
+            "Really gotta call Mom (A) @phone @someday",
+            "(b) Get back to the boss",
+            "(B)->Submit TPS report",
+            "xylophone lesson",
+            "X 2012-01-01 Make resolutions",
+        ] {
+            let task = only_task(text);
+            assert_eq!(task.priority, Priority::None, "{text}");
+            assert!(!task.completed, "{text}");
+        }
+
+        let task = only_task("2011-03-02 Document +TodoTxt task format");
+        assert_eq!(task.created_at, date("2011-03-02"));
+        assert_eq!(task.content, "Document task format");
+
+        let task = only_task("(A) 2011-03-02 Call Mom");
+        assert_eq!(task.priority, Priority::Urgent);
+        assert_eq!(task.created_at, date("2011-03-02"));
+
+        let task = only_task("(A) Call Mom 2011-03-02");
+        assert_eq!(
+            (task.created_at, task.content.as_str()),
+            (0, "Call Mom 2011-03-02")
+        );
+
+        let collections = round_trip("(A) Call Mom +Family +PeaceLoveAndHappiness @iphone @phone");
+        assert_eq!(collections[0].title, "PeaceLoveAndHappiness");
+        assert_eq!(collections[0].tasks_data[0].content, "Call Mom +Family");
+        assert_eq!(collections[0].tasks_data[0].tags, ["iphone", "phone"]);
+
+        let task = only_task("x 2011-03-03 Call Mom");
+        assert!(task.completed);
+        assert_eq!(task.modified_at, date("2011-03-03"));
+
+        let task = only_task("(A) x Find ticket prices");
+        assert!(!task.completed);
+        assert_eq!(task.content, "x Find ticket prices");
+
+        let task =
+            only_task("x 2011-03-02 2011-03-01 Review Tim's pull request +TodoTxtTouch @github");
+        assert!(task.completed);
+        assert_eq!(task.modified_at, date("2011-03-02"));
+        assert_eq!(task.created_at, date("2011-03-01"));
+        assert_eq!(task.content, "Review Tim's pull request");
+    }
+
+    #[test]
+    fn extras() {
+        let task = only_task("Pay rent due:2026-11-01 time:09:30 rec:+1m");
+        assert_eq!(task.due_date.as_deref(), Some("2026-11-01"));
+        assert_eq!(task.due_time.as_deref(), Some("09:30"));
+        assert_eq!(
+            task.recurrence,
+            Some(Recurrence::Monthly {
+                interval: 1,
+                day: 1
+            })
+        );
+
+        let task = only_task("x 2026-10-18 Water plants rec:2w pri:B");
+        assert_eq!(
+            task.recurrence,
+            Some(Recurrence::AfterCompletion { days: 14 })
+        );
+        assert_eq!(task.priority, Priority::High);
+
+        // Other extras and ones that don't parse stay in the content
+        let task = only_task("Call tel:555-0100 due:someday time:09:30 rec:often");
+        assert_eq!(
+            task.content,
+            "Call tel:555-0100 due:someday rec:often time:09:30"
+        );
+        assert_eq!(task.due_date, None);
+        assert_eq!(task.recurrence, None);
+    }
+
+    #[test]
+    fn absurd_recurrence_stays_text() {
+        for rule in ["999999999w", "4294967295d", "2000d", "+1001d", "143w", "0d"] {
+            let text = format!("Stretch rec:{rule}");
+            let task = only_task(&text);
+            assert_eq!(task.recurrence, None, "{rule}");
+            assert_eq!(task.content, text);
+        }
+        assert_eq!(
+            import_rule("1000d", None),
+            Some(Recurrence::AfterCompletion { days: 1000 })
+        );
+        assert_eq!(
+            import_rule("142w", None),
+            Some(Recurrence::AfterCompletion { days: 994 })
+        );
+    }
+
+    #[test]
+    fn subtasks() {
+        let collections = round_trip(
+            "Plan the trip id:1 +Travel\n\
+             Pick a flight id:2 p:1 +Travel\n\
+             Compare prices p:2 +Travel\n\
+             Book a hotel p:1 +Travel\n\
+             Orphan p:9\n",
+        );
+        let trip = &collections[0].tasks_data[0];
+        assert_eq!(trip.subtasks.len(), 2);
+        assert_eq!(trip.subtasks[0].subtasks[0].content, "Compare prices");
+        assert_eq!(collections[1].tasks_data[0].content, "Orphan p:9");
+    }
+
+    #[test]
+    fn content_that_looks_like_markup_survives() {
+        for content in [
+            "x marks the spot",
+            "(A) is the best grade",
+            "2026-10-18 was a Sunday",
+            "Buy +1 cables for @home and +more",
+            "Ask due:tomorrow at the desk",
+            "id:7 and p:3 are taken",
+            "pri:A rec:1d time:09:00",
+            "\\x is not escaped twice, \\+a is",
+            "Lone + and @ signs",
+            "C:\\path and 10:30",
+        ] {
+            for (completed, priority) in [(false, Priority::None), (true, Priority::High)] {
+                let collections = vec![CollectionData {
+                    id: String::new(),
+                    title: String::new(),
+                    tasks_data: vec![TaskData {
+                        content: content.to_owned(),
+                        completed,
+                        priority,
+                        ..TaskData::default()
+                    }],
+                }];
+                let exported = export(&collections, utc_date);
+                let task = &parse(&exported)[0].tasks_data[0];
+                assert_eq!(task.content, content, "{exported}");
+                assert_eq!(task.completed, completed, "{exported}");
+                assert_eq!(task.priority, priority, "{exported}");
+                assert_eq!(task.created_at, 0, "{exported}");
+            }
+        }
+    }
+
+    #[test]
+    fn writes_escapes_only_where_needed() {
+        let collections = vec![CollectionData {
+            id: String::new(),
+            title: "Home Office".to_owned(),
+            tasks_data: vec![TaskData {
+                content: "x then y, and x +z at 10:30".to_owned(),
+                tags: vec!["desk".to_owned()],
+                ..TaskData::default()
+            }],
+        }];
+        assert_eq!(
+            export(&collections, utc_date),
+            "\\x then y, and x \\+z at 10:30 @desk +Home-Office\n"
+        );
+    }
+
+    #[test]
+    fn leaves_out_dates_it_cannot_tell() {
+        let collections = vec![CollectionData {
+            id: String::new(),
+            title: String::new(),
+            tasks_data: vec![TaskData {
+                content: "Odd times".to_owned(),
+                completed: true,
+                created_at: i64::MAX,
+                modified_at: date("2026-10-18"),
+                ..TaskData::default()
+            }],
+        }];
+        assert_eq!(export(&collections, utc_date), "x 2026-10-18 Odd times\n");
+        assert_eq!(export(&collections, |_| None), "x Odd times\n");
+    }
+}
@@ -1,4 +1,5 @@
 use crate::config::APP_ID;
+use crate::date::{Date, Time};
 use std::path::PathBuf;
 
 pub fn data_path() -> PathBuf {
@@ -8,3 +9,55 @@ pub fn data_path() -> PathBuf {
     path.push("data.json");
     path
 }
+
+/// Splits a `glib::DateTime` into the calendar types used by the data model.
+pub fn split_date_time(date_time: &glib::DateTime) -> (Date, Time) {
+    let date = Date::new(
+        date_time.year(),
+        date_time.month() as u32,
+        date_time.day_of_month() as u32,
+    )
+    .expect("GLib returned an invalid date");
+    let time = Time::new(date_time.hour() as u32, date_time.minute() as u32)
+        .expect("GLib returned an invalid time");
+    (date, time)
+}
+
+/// The local `glib::DateTime` at `time` on `date`, or at midnight without a time.
+pub fn local_date_time(date: Date, time: Option<Time>) -> Option<glib::DateTime> {
+    let (hour, minute) = time.map_or((0, 0), |time| (time.hour(), time.minute()));
+    glib::DateTime::from_local(
+        date.year(),
+        date.month() as i32,
+        date.day() as i32,
+        hour as i32,
+        minute as i32,
+        0.0,
+    )
+    .ok()
+}
+
+/// The local date and time of `time` on `date` in UTC, or the same ones if
+/// GLib can't tell.
+pub fn utc_to_local(date: Date, time: Time) -> (Date, Time) {
+    glib::DateTime::from_utc(
+        date.year(),
+        date.month() as i32,
+        date.day() as i32,
+        time.hour() as i32,
+        time.minute() as i32,
+        0.0,
+    )
+    .and_then(|date_time| date_time.to_local())
+    .map_or((date, time), |date_time| split_date_time(&date_time))
+}
+
+/// A human readable description of a due date, like "Due Tue 20 Oct, 09:00".
+pub fn format_due(date: Date, time: Option<Time>) -> Option<String> {
+    let day = local_date_time(date, None)?.format("%a %-d %b").ok()?;
+
+    Some(match time {
+        Some(time) => format!("Due {day}, {time}"),
+        None => format!("Due {day}"),
+    })
+}
@@ -12,6 +12,8 @@ pub mod imp {
     #[derive(glib::Properties, Default)]
     #[properties(wrapper_type = super::CollectionObject)]
     pub struct CollectionObject {
+        #[property(get, construct_only)]
+        pub id: OnceCell<String>,
         #[property(get, set)]
         pub title: RefCell<String>,
         #[property(get, set)]
@@ -34,13 +36,19 @@ glib::wrapper! {
 
 impl CollectionObject {
     pub fn new(title: &str, tasks: gio::ListStore) -> Self {
+        Self::with_id(&glib::uuid_string_random(), title, tasks)
+    }
+
+    fn with_id(id: &str, title: &str, tasks: gio::ListStore) -> Self {
         glib::Object::builder()
+            .property("id", id)
             .property("title", title)
             .property("tasks", tasks)
             .build()
     }
 
     pub fn to_collection_data(&self) -> CollectionData {
+        let id = self.id();
         let title = self.title();
         let tasks_data = self
             .tasks()
@@ -48,7 +56,24 @@ impl CollectionObject {
             .filter_map(Result::ok)
             .map(|task| task.to_task_data())
             .collect();
-        CollectionData { title, tasks_data }
+        CollectionData {
+            id,
+            title,
+            tasks_data,
+        }
+    }
+
+    /// Finds the task with `id`, whether top-level or a subtask at any depth.
+    pub fn find_task(&self, id: &str) -> Option<TaskObject> {
+        find_task_in(&self.tasks(), id)
+    }
+
+    /// Every task with the task it is a subtask of, parents before their
+    /// subtasks.
+    pub fn tasks_with_parents(&self) -> Vec<(Option<TaskObject>, TaskObject)> {
+        let mut tasks = Vec::new();
+        collect_tasks(&self.tasks(), None, &mut tasks);
+        tasks
     }
 
     pub fn from_collection_data(data: CollectionData) -> Self {
@@ -62,12 +87,99 @@ impl CollectionObject {
         let tasks_store = gio::ListStore::new::<TaskObject>();
         tasks_store.extend_from_slice(&tasks);
 
-        Self::new(&title, tasks_store)
+        Self::with_id(&data.id, &title, tasks_store)
     }
 }
 
+fn collect_tasks(
+    store: &gio::ListStore,
+    parent: Option<&TaskObject>,
+    tasks: &mut Vec<(Option<TaskObject>, TaskObject)>,
+) {
+    for task in store.iter::<TaskObject>().filter_map(Result::ok) {
+        tasks.push((parent.cloned(), task.clone()));
+        collect_tasks(&task.subtasks(), Some(&task), tasks);
+    }
+}
+
+fn find_task_in(tasks: &gio::ListStore, id: &str) -> Option<TaskObject> {
+    tasks
+        .iter::<TaskObject>()
+        .filter_map(Result::ok)
+        .find_map(|task| {
+            if task.id() == id {
+                Some(task)
+            } else {
+                find_task_in(&task.subtasks(), id)
+            }
+        })
+}
+
 #[derive(Default, Clone, Serialize, Deserialize)]
 pub struct CollectionData {
+    /// Random UUID that stays the same for the whole life of the collection
+    pub id: String,
     pub title: String,
     pub tasks_data: Vec<TaskData>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: &str, subtasks: Vec<TaskData>) -> TaskData {
+        TaskData {
+            id: id.to_owned(),
+            content: id.to_owned(),
+            subtasks,
+            ..TaskData::default()
+        }
+    }
+
+    #[test]
+    fn finds_tasks_at_any_depth() {
+        let collection = CollectionObject::from_collection_data(CollectionData {
+            id: "collection".to_owned(),
+            title: "Chores".to_owned(),
+            tasks_data: vec![
+                task(
+                    "a",
+                    vec![task("a1", vec![]), task("a2", vec![task("a2i", vec![])])],
+                ),
+                task("b", vec![]),
+            ],
+        });
+
+        for id in ["a", "a1", "a2", "a2i", "b"] {
+            let task = collection.find_task(id).unwrap_or_else(|| panic!("{id}"));
+            assert_eq!(task.content(), id);
+        }
+        assert!(collection.find_task("c").is_none());
+    }
+
+    #[test]
+    fn lists_parents_before_subtasks() {
+        let collection = CollectionObject::from_collection_data(CollectionData {
+            id: "collection".to_owned(),
+            title: "Chores".to_owned(),
+            tasks_data: vec![
+                task("a", vec![task("a1", vec![task("a1i", vec![])])]),
+                task("b", vec![]),
+            ],
+        });
+
+        let tasks = collection
+            .tasks_with_parents()
+            .into_iter()
+            .map(|(parent, task)| (parent.map(|parent| parent.id()), task.id()))
+            .collect::<Vec<_>>();
+        let expected = [
+            (None, "a"),
+            (Some("a"), "a1"),
+            (Some("a1"), "a1i"),
+            (None, "b"),
+        ]
+        .map(|(parent, id)| (parent.map(str::to_owned), id.to_owned()));
+        assert_eq!(tasks, expected);
+    }
+}
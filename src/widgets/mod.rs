@@ -1,9 +1,15 @@
 mod collection_object;
+mod priority;
+mod saved_filter_object;
+mod smart_collection;
 mod task_object;
 mod window;
 
 pub use {
     collection_object::{CollectionData, CollectionObject},
+    priority::Priority,
+    saved_filter_object::{SavedFilterData, SavedFilterObject},
+    smart_collection::{CollectionSource, SmartCollection},
     task_object::{TaskData, TaskObject},
     window::Window,
 };
@@ -0,0 +1,86 @@
+use gtk::glib;
+use serde::{Deserialize, Serialize};
+
+/// How urgent a task is, from least to most.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    glib::Enum,
+)]
+#[enum_type(name = "TodoPriority")]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    /// Parses a level name like "high", in any case. "med" is short for "medium".
+    pub fn parse(name: &str) -> Option<Self> {
+        let priority = match name.to_lowercase().as_str() {
+            "none" => Priority::None,
+            "low" => Priority::Low,
+            "med" | "medium" => Priority::Medium,
+            "high" => Priority::High,
+            "urgent" => Priority::Urgent,
+            _ => return None,
+        };
+        Some(priority)
+    }
+
+    /// The lowercase name `parse` reads, like "high".
+    pub fn name(self) -> &'static str {
+        match self {
+            Priority::None => "none",
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        }
+    }
+
+    /// The next level up, wrapping around from `Urgent` back to `None`.
+    pub fn next(self) -> Self {
+        match self {
+            Priority::None => Priority::Low,
+            Priority::Low => Priority::Medium,
+            Priority::Medium => Priority::High,
+            Priority::High => Priority::Urgent,
+            Priority::Urgent => Priority::None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Priority::None => "No Priority",
+            Priority::Low => "Low Priority",
+            Priority::Medium => "Medium Priority",
+            Priority::High => "High Priority",
+            Priority::Urgent => "Urgent",
+        }
+    }
+
+    /// Style class of the row indicator, `None` when there is nothing to show.
+    pub fn css_class(self) -> Option<&'static str> {
+        match self {
+            Priority::None => None,
+            Priority::Low => Some("priority-low"),
+            Priority::Medium => Some("priority-medium"),
+            Priority::High => Some("priority-high"),
+            Priority::Urgent => Some("priority-urgent"),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use adw::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::query::Query;
+
+pub mod imp {
+    use adw::subclass::prelude::*;
+    use std::cell::{OnceCell, RefCell};
+
+    use super::*;
+
+    #[derive(glib::Properties, Default)]
+    #[properties(wrapper_type = super::SavedFilterObject)]
+    pub struct SavedFilterObject {
+        #[property(get, construct_only)]
+        pub id: OnceCell<String>,
+        #[property(get, set)]
+        pub name: RefCell<String>,
+        #[property(get, set)]
+        pub query: RefCell<String>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SavedFilterObject {
+        const NAME: &'static str = "TodoSavedFilterObject";
+        type Type = super::SavedFilterObject;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for SavedFilterObject {}
+}
+
+glib::wrapper! {
+    pub struct SavedFilterObject(ObjectSubclass<imp::SavedFilterObject>);
+}
+
+impl SavedFilterObject {
+    pub fn new(name: &str, query: &str) -> Self {
+        Self::with_id(&glib::uuid_string_random(), name, query)
+    }
+
+    fn with_id(id: &str, name: &str, query: &str) -> Self {
+        glib::Object::builder()
+            .property("id", id)
+            .property("name", name)
+            .property("query", query)
+            .build()
+    }
+
+    /// The parsed query, `None` if the saved text does not parse.
+    pub fn parsed_query(&self) -> Option<Query> {
+        self.query().parse().ok()
+    }
+
+    pub fn to_saved_filter_data(&self) -> SavedFilterData {
+        SavedFilterData {
+            id: self.id(),
+            name: self.name(),
+            query: self.query(),
+        }
+    }
+
+    pub fn from_saved_filter_data(data: SavedFilterData) -> Self {
+        Self::with_id(&data.id, &data.name, &data.query)
+    }
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct SavedFilterData {
+    /// Random UUID that stays the same for the whole life of the filter
+    pub id: String,
+    pub name: String,
+    /// Kept as typed, see `Query` for the syntax
+    pub query: String,
+}
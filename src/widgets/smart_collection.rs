@@ -0,0 +1,104 @@
+use gtk::glib;
+
+use crate::date::Date;
+use crate::utils::split_date_time;
+use crate::widgets::{CollectionObject, SavedFilterObject, TaskObject};
+
+/// A built-in view pinned above the collections that gathers tasks from all
+/// of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmartCollection {
+    Today,
+    Upcoming,
+    Overdue,
+    AllTasks,
+}
+
+impl SmartCollection {
+    /// In sidebar order.
+    pub const ALL: [SmartCollection; 4] = [
+        SmartCollection::Today,
+        SmartCollection::Upcoming,
+        SmartCollection::Overdue,
+        SmartCollection::AllTasks,
+    ];
+
+    /// Stable name, used as action target.
+    pub fn id(self) -> &'static str {
+        match self {
+            SmartCollection::Today => "today",
+            SmartCollection::Upcoming => "upcoming",
+            SmartCollection::Overdue => "overdue",
+            SmartCollection::AllTasks => "all",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|smart| smart.id() == id)
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            SmartCollection::Today => "Today",
+            SmartCollection::Upcoming => "Upcoming",
+            SmartCollection::Overdue => "Overdue",
+            SmartCollection::AllTasks => "All Tasks",
+        }
+    }
+
+    pub fn icon_name(self) -> &'static str {
+        match self {
+            SmartCollection::Today => "x-office-calendar-symbolic",
+            SmartCollection::Upcoming => "document-open-recent-symbolic",
+            SmartCollection::Overdue => "dialog-warning-symbolic",
+            SmartCollection::AllTasks => "view-list-symbolic",
+        }
+    }
+
+    /// Whether `task` shows up in this view at `now`.
+    pub fn contains(self, task: &TaskObject, now: &glib::DateTime) -> bool {
+        let (today, _) = split_date_time(now);
+        let due_date = task.due().map(|(date, _)| date);
+        match self {
+            SmartCollection::Today => due_date == Some(today),
+            SmartCollection::Upcoming => due_date.is_some_and(|date| date > today),
+            SmartCollection::Overdue => task.is_overdue(now),
+            SmartCollection::AllTasks => true,
+        }
+    }
+
+    /// Due date for a task added while this view is shown and without one of
+    /// its own, so it doesn't vanish right away.
+    pub fn default_due_date(self, today: Date) -> Option<Date> {
+        match self {
+            SmartCollection::Today => Some(today),
+            _ => None,
+        }
+    }
+}
+
+/// What the task list shows: a collection, a smart collection or a saved filter.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CollectionSource {
+    Collection(CollectionObject),
+    Smart(SmartCollection),
+    SavedFilter(SavedFilterObject),
+}
+
+impl From<CollectionObject> for CollectionSource {
+    fn from(collection: CollectionObject) -> Self {
+        CollectionSource::Collection(collection)
+    }
+}
+
+impl From<SmartCollection> for CollectionSource {
+    fn from(smart: SmartCollection) -> Self {
+        CollectionSource::Smart(smart)
+    }
+}
+
+impl From<SavedFilterObject> for CollectionSource {
+    fn from(saved_filter: SavedFilterObject) -> Self {
+        CollectionSource::SavedFilter(saved_filter)
+    }
+}
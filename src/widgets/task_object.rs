@@ -1,21 +1,51 @@
+use adw::prelude::*;
 use adw::subclass::prelude::*;
-use glib::Object;
-use gtk::glib;
+use glib::{clone, Object};
+use gtk::{gio, glib};
 use serde::{Deserialize, Serialize};
 
+use crate::date::{Date, Time};
+use crate::query::Query;
+use crate::quick_add::QuickAdd;
+use crate::recurrence::Recurrence;
+use crate::utils::{format_due, split_date_time};
+use crate::widgets::Priority;
+
 pub mod imp {
     use gtk::prelude::*;
     use gtk::subclass::prelude::*;
-    use std::cell::RefCell;
+    use std::cell::{OnceCell, RefCell};
 
     use super::*;
 
     #[derive(glib::Properties, Default)]
     #[properties(wrapper_type = super::TaskObject)]
     pub struct TaskObject {
+        #[property(name = "id", get, construct_only, type = String, member = id)]
         #[property(name = "completed", get, set, type = bool, member = completed)]
         #[property(name = "content", get, set, type = String, member = content)]
+        #[property(name = "notes", get, set, type = String, member = notes)]
+        #[property(name = "due-date", get, set, nullable, type = Option<String>, member = due_date)]
+        #[property(name = "due-time", get, set, nullable, type = Option<String>, member = due_time)]
+        #[property(name = "priority", get, set, type = Priority, member = priority, builder(Priority::None))]
+        #[property(name = "tags", get, set, type = Vec<String>, member = tags)]
+        #[property(name = "recurrence", get = Self::recurrence, nullable, type = Option<String>)]
+        #[property(name = "created-at", get, construct_only, type = i64, member = created_at)]
+        #[property(name = "modified-at", get, set, type = i64, member = modified_at)]
         pub data: RefCell<TaskData>,
+        // Source of truth for the children, `data.subtasks` stays empty
+        #[property(get)]
+        pub subtasks: OnceCell<gio::ListStore>,
+    }
+
+    impl TaskObject {
+        // The property shows the rule in its text form, see `Recurrence`'s
+        // `Display`. It is set with `set_recurrence_rule`, so rules that
+        // don't parse back, like ones read from elsewhere, are kept as they are
+        fn recurrence(&self) -> Option<String> {
+            let data = self.data.borrow();
+            data.recurrence.as_ref().map(ToString::to_string)
+        }
     }
 
     #[glib::object_subclass]
@@ -25,7 +55,15 @@ pub mod imp {
     }
 
     #[glib::derived_properties]
-    impl ObjectImpl for TaskObject {}
+    impl ObjectImpl for TaskObject {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            self.subtasks
+                .set(gio::ListStore::new::<super::TaskObject>())
+                .expect("Subtasks already set.");
+        }
+    }
 }
 
 glib::wrapper! {
@@ -34,27 +72,346 @@ glib::wrapper! {
 
 impl TaskObject {
     pub fn new(completed: bool, content: String) -> Self {
-        Object::builder()
+        let now = unix_now();
+        let task: Self = Object::builder()
+            .property("id", glib::uuid_string_random())
             .property("completed", completed)
             .property("content", content)
-            .build()
+            .property("created-at", now)
+            .property("modified-at", now)
+            .build();
+        task.track_modifications();
+        task.track_recurrence();
+        task
+    }
+
+    /// A new task from text typed like into the task entry, and what
+    /// `quick_add::parse` made of it. Recurring tasks that start out
+    /// `completed` stay that way.
+    pub fn from_quick_add(text: String, quick_add: QuickAdd, completed: bool) -> Self {
+        // A task made of nothing but recognized words keeps them as its content
+        let content = if quick_add.content.is_empty() {
+            text
+        } else {
+            quick_add.content
+        };
+
+        let now = unix_now();
+        Self::from_task_data(TaskData {
+            id: glib::uuid_string_random().to_string(),
+            completed,
+            content,
+            due_date: quick_add.due_date.map(|date| date.to_string()),
+            due_time: quick_add.due_time.map(|time| time.to_string()),
+            priority: quick_add.priority,
+            tags: quick_add.tags,
+            recurrence: quick_add.recurrence,
+            created_at: now,
+            modified_at: now,
+            ..TaskData::default()
+        })
     }
 
     pub fn from_task_data(data: TaskData) -> Self {
-        Self::new(data.completed, data.content)
+        let task: Self = Object::builder()
+            .property("id", data.id)
+            .property("completed", data.completed)
+            .property("content", data.content)
+            .property("notes", data.notes)
+            .property("due-date", data.due_date)
+            .property("due-time", data.due_time)
+            .property("priority", data.priority)
+            .property("tags", data.tags)
+            .property("created-at", data.created_at)
+            .property("modified-at", data.modified_at)
+            .build();
+        {
+            let mut task_data = task.imp().data.borrow_mut();
+            task_data.recurrence = data.recurrence;
+            task_data.completions = data.completions;
+        }
+        task.track_modifications();
+        task.track_recurrence();
+
+        let subtasks = data
+            .subtasks
+            .into_iter()
+            .map(TaskObject::from_task_data)
+            .collect::<Vec<TaskObject>>();
+        task.subtasks().extend_from_slice(&subtasks);
+
+        task
+    }
+
+    /// Bumps `modified-at` whenever something the user edits changes. Only
+    /// connected once the task is built, so restoring it is not a change.
+    fn track_modifications(&self) {
+        for property in [
+            "completed",
+            "content",
+            "due-date",
+            "due-time",
+            "priority",
+            "tags",
+            "recurrence",
+            "notes",
+        ] {
+            self.connect_notify_local(Some(property), |task, _| {
+                task.set_modified_at(unix_now());
+            });
+        }
+    }
+
+    /// Completing a recurring task records the completion and reopens it at
+    /// its next due date.
+    fn track_recurrence(&self) {
+        self.connect_completed_notify(|task| {
+            if task.is_completed() && task.recurrence_rule().is_some() {
+                // Reopen it once whatever completed it, like a check button, is done
+                glib::idle_add_local_once(clone!(
+                    #[weak]
+                    task,
+                    move || {
+                        task.roll_forward();
+                    }
+                ));
+            }
+        });
+    }
+
+    fn roll_forward(&self) {
+        // It may have been reopened by hand in the meantime
+        let Some(recurrence) = self.recurrence_rule().filter(|_| self.is_completed()) else {
+            return;
+        };
+
+        let now = glib::DateTime::now_local().expect("Could not get the local time");
+        let (today, _) = split_date_time(&now);
+        let due = self.due().map_or(today, |(date, _)| date);
+        // A rule that runs out of dates just leaves the task done
+        let Some(next_due) = recurrence.next_due(due, today) else {
+            return;
+        };
+
+        self.imp()
+            .data
+            .borrow_mut()
+            .completions
+            .push(today.to_string());
+        self.set_due_date(Some(next_due.to_string()));
+        for subtask in self.subtasks().iter::<TaskObject>().filter_map(Result::ok) {
+            subtask.set_completed(false);
+        }
+        self.set_completed(false);
+    }
+
+    pub fn recurrence_rule(&self) -> Option<Recurrence> {
+        self.imp().data.borrow().recurrence.clone()
+    }
+
+    pub fn set_recurrence_rule(&self, recurrence: Option<Recurrence>) {
+        self.imp().data.borrow_mut().recurrence = recurrence;
+        self.notify_recurrence();
     }
 
     pub fn to_task_data(&self) -> TaskData {
-        self.imp().data.borrow().clone()
+        let mut data = self.imp().data.borrow().clone();
+        data.subtasks = self
+            .subtasks()
+            .iter::<TaskObject>()
+            .filter_map(Result::ok)
+            .map(|subtask| subtask.to_task_data())
+            .collect();
+        data
     }
 
     pub fn is_completed(&self) -> bool {
         self.imp().data.borrow().completed
     }
+
+    /// Whether the task matches `query`, taking relative dates from `today`.
+    pub fn matches(&self, query: &Query, today: Date) -> bool {
+        query.matches(&self.imp().data.borrow(), today)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.imp().data.borrow().tags.iter().any(|t| t == tag)
+    }
+
+    /// How many subtasks are completed, out of how many.
+    pub fn subtask_progress(&self) -> (usize, usize) {
+        let subtasks = self.subtasks();
+        let completed = subtasks
+            .iter::<TaskObject>()
+            .filter_map(Result::ok)
+            .filter(TaskObject::is_completed)
+            .count();
+        (completed, subtasks.n_items() as usize)
+    }
+
+    /// The due date, and time if one is set. Unparseable values count as unset.
+    pub fn due(&self) -> Option<(Date, Option<Time>)> {
+        let data = self.imp().data.borrow();
+        let date = Date::parse(data.due_date.as_deref()?)?;
+        let time = data.due_time.as_deref().and_then(Time::parse);
+        Some((date, time))
+    }
+
+    /// Whether the task is still open past its due date (or due time, when set).
+    pub fn is_overdue(&self, now: &glib::DateTime) -> bool {
+        if self.is_completed() {
+            return false;
+        }
+        let Some((date, time)) = self.due() else {
+            return false;
+        };
+
+        let (today, now) = split_date_time(now);
+        match time {
+            Some(time) if date == today => time < now,
+            _ => date < today,
+        }
+    }
+
+    /// A human readable description of the due date, like "Due Tue 20 Oct, 09:00".
+    pub fn due_label(&self) -> Option<String> {
+        let (date, time) = self.due()?;
+        format_due(date, time)
+    }
+}
+
+fn unix_now() -> i64 {
+    glib::real_time() / 1_000_000
 }
 
 #[derive(Default, Clone, Debug, Serialize, Deserialize)]
 pub struct TaskData {
+    /// Random UUID that stays the same for the whole life of the task
+    pub id: String,
     pub completed: bool,
     pub content: String,
+    /// Free-form Markdown, may span several lines
+    pub notes: String,
+    /// `YYYY-MM-DD`
+    pub due_date: Option<String>,
+    /// `HH:MM`, only meaningful together with `due_date`
+    pub due_time: Option<String>,
+    pub priority: Priority,
+    /// Lowercase names without the leading `#`
+    pub tags: Vec<String>,
+    pub recurrence: Option<Recurrence>,
+    /// `YYYY-MM-DD` of every completion of a recurring task
+    pub completions: Vec<String>,
+    /// Unix time in seconds, 0 for tasks from before it was recorded
+    pub created_at: i64,
+    /// Unix time in seconds of the last edit, 0 if there was none since it was recorded
+    pub modified_at: i64,
+    pub subtasks: Vec<TaskData>,
+}
+
+impl TaskData {
+    /// The same task with new IDs for it and its subtasks, for tasks brought
+    /// in from elsewhere whose IDs could clash with existing ones.
+    pub fn with_new_ids(mut self) -> Self {
+        self.id = glib::uuid_string_random().to_string();
+        self.subtasks = self
+            .subtasks
+            .into_iter()
+            .map(TaskData::with_new_ids)
+            .collect();
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quick_add;
+
+    /// Runs what completing tasks leaves for later.
+    fn settle(context: &glib::MainContext) {
+        while context.iteration(false) {}
+    }
+
+    fn quick_add_task(text: &str, completed: bool) -> TaskObject {
+        let today = Date::new(2026, 10, 20).unwrap();
+        let now = Time::new(10, 0).unwrap();
+        TaskObject::from_quick_add(
+            text.to_owned(),
+            quick_add::parse(text, today, now),
+            completed,
+        )
+    }
+
+    #[test]
+    fn recurring_tasks_added_done_stay_done() {
+        // Where completed tasks are rolled forward
+        let context = glib::MainContext::default();
+        let _owner = context.acquire().unwrap();
+
+        let task = quick_add_task("Water plants tomorrow every day", true);
+        settle(&context);
+        assert!(task.is_completed());
+        assert_eq!(task.due_date().as_deref(), Some("2026-10-21"));
+        assert!(task.to_task_data().completions.is_empty());
+
+        // Completing it afterwards still rolls it forward
+        let task = quick_add_task("Water plants tomorrow every day", false);
+        task.set_completed(true);
+        settle(&context);
+        assert!(!task.is_completed());
+        assert_eq!(task.to_task_data().completions.len(), 1);
+    }
+
+    #[test]
+    fn keeps_rules_beyond_what_parses() {
+        let rule = Recurrence::Daily { interval: 5000 };
+        let task = TaskObject::from_task_data(TaskData {
+            recurrence: Some(rule.clone()),
+            ..TaskData::default()
+        });
+        assert_eq!(task.recurrence_rule(), Some(rule.clone()));
+        assert_eq!(task.to_task_data().recurrence, Some(rule));
+        assert_eq!(task.recurrence().as_deref(), Some("every 5000 days"));
+    }
+
+    #[test]
+    fn rolls_forward_across_daylight_saving_time() {
+        let berlin = glib::TimeZone::from_identifier(Some("Europe/Berlin")).unwrap();
+        let moment = |(date, time): (Date, Option<Time>)| {
+            let time = time.unwrap();
+            glib::DateTime::new(
+                &berlin,
+                date.year(),
+                date.month() as i32,
+                date.day() as i32,
+                time.hour() as i32,
+                time.minute() as i32,
+                0.0,
+            )
+            .unwrap()
+        };
+
+        // Clocks go forward on 29 March and back on 25 October 2026
+        for (due_date, interval, next_date, hours) in [
+            ("2026-03-28", 1, "2026-03-29", 23),
+            ("2026-10-24", 2, "2026-10-26", 49),
+        ] {
+            let task = TaskObject::from_task_data(TaskData {
+                completed: true,
+                due_date: Some(due_date.to_owned()),
+                due_time: Some("09:00".to_owned()),
+                recurrence: Some(Recurrence::Daily { interval }),
+                ..TaskData::default()
+            });
+            let before = moment(task.due().unwrap());
+            task.roll_forward();
+
+            assert!(!task.is_completed());
+            assert_eq!(task.due_date().as_deref(), Some(next_date));
+            assert_eq!(task.due_time().as_deref(), Some("09:00"));
+            let after = moment(task.due().unwrap());
+            assert_eq!(after.difference(&before).as_hours(), hours);
+        }
+    }
 }
@@ -1,16 +1,40 @@
-use crate::utils::data_path;
-use crate::widgets::{CollectionData, CollectionObject, TaskObject};
+use std::cell::{OnceCell, RefCell};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::config::LOG_DOMAIN;
+use crate::date::Time;
+use crate::ical;
+use crate::markdown::{self, Style};
+use crate::persistence::{self, Autosave};
+use crate::query::Query;
+use crate::quick_add::{self, QuickAdd};
+use crate::recurrence::{self, Recurrence};
+use crate::reminders::{Reminders, SystemClock};
+use crate::todotxt;
+use crate::utils::{format_due, local_date_time, split_date_time, utc_to_local};
+use crate::widgets::{
+    CollectionObject, CollectionSource, Priority, SavedFilterObject, SmartCollection, TaskData,
+    TaskObject,
+};
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::property::PropertyGet;
 use glib::Object;
 use glib::{clone, subclass};
-use gtk::{gio, glib, pango, CustomFilter, FilterListModel, NoSelection};
+use gtk::{
+    gdk, gio, glib, pango, CustomFilter, CustomSorter, FilterListModel, FlattenListModel,
+    MapListModel, NoSelection, SortListModel,
+};
 
 pub mod imp {
     use adw::prelude::*;
     use adw::subclass::prelude::*;
-    use std::cell::{OnceCell, RefCell};
+    use std::cell::{Cell, OnceCell, RefCell};
+    use std::rc::Rc;
 
     use super::*;
 
@@ -20,18 +44,54 @@ pub mod imp {
         #[template_child]
         pub entry: TemplateChild<gtk::Entry>,
         #[template_child]
+        pub quick_add_preview: TemplateChild<gtk::Box>,
+        #[template_child]
         pub tasks_list: TemplateChild<gtk::ListBox>,
         #[template_child]
+        pub smart_collections_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
         pub collections_list: TemplateChild<gtk::ListBox>,
         #[template_child]
         pub split_view: TemplateChild<adw::NavigationSplitView>,
         #[template_child]
         pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub search_bar: TemplateChild<gtk::SearchBar>,
+        #[template_child]
+        pub search_entry: TemplateChild<gtk::SearchEntry>,
+        #[template_child]
+        pub search_all_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub tasks_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub search_results: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub tags_section: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub tags_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub saved_filters_section: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub saved_filters_list: TemplateChild<gtk::ListBox>,
 
         pub collections: OnceCell<gio::ListStore>,
-        pub current_collection: RefCell<Option<CollectionObject>>,
+        pub saved_filters: OnceCell<gio::ListStore>,
+        pub current_collection: RefCell<Option<CollectionSource>>,
+        pub current_source_filter: RefCell<Option<CustomFilter>>,
         pub current_filter_model: RefCell<Option<FilterListModel>>,
-        pub tasks_changed_handler_id: RefCell<Option<glib::SignalHandlerId>>,
+        pub current_sort_model: RefCell<Option<SortListModel>>,
+        pub search_filter: OnceCell<gtk::StringFilter>,
+        pub current_tag: RefCell<Option<String>>,
+        pub tasks_changed_handler: RefCell<Option<(gio::ListModel, glib::SignalHandlerId)>>,
+        pub all_tasks: OnceCell<FlattenListModel>,
+        pub autosave: OnceCell<Rc<Autosave>>,
+        pub reminders: OnceCell<Rc<Reminders<SystemClock>>>,
+        pub close_without_saving: Cell<bool>,
+        pub task_menu: OnceCell<gtk::PopoverMenu>,
+        pub collection_menu: OnceCell<gtk::PopoverMenu>,
+        pub saved_filter_menu: OnceCell<gtk::PopoverMenu>,
 
         pub settings: OnceCell<gio::Settings>,
     }
@@ -52,6 +112,218 @@ pub mod imp {
             klass.install_action_async("win.new-collection", None, |window, _, _| async move {
                 window.new_collection().await;
             });
+
+            klass.install_action_async(
+                "win.rename-collection",
+                Some(&String::static_variant_type()),
+                |window, _, parameter| async move {
+                    let id = parameter
+                        .and_then(|parameter| parameter.get::<String>())
+                        .expect("Collection ID parameter is not a string");
+                    if let Some(collection) = window.action_collection(&id) {
+                        window.rename_collection(collection).await;
+                    }
+                },
+            );
+
+            klass.install_action_async(
+                "win.delete-collection",
+                Some(&String::static_variant_type()),
+                |window, _, parameter| async move {
+                    let id = parameter
+                        .and_then(|parameter| parameter.get::<String>())
+                        .expect("Collection ID parameter is not a string");
+                    if let Some(collection) = window.action_collection(&id) {
+                        window.delete_collection(collection).await;
+                    }
+                },
+            );
+
+            klass.install_action_async("win.import-tasks", None, |window, _, _| async move {
+                window.import_tasks().await;
+            });
+
+            klass.install_action_async(
+                "win.export-collection",
+                Some(&String::static_variant_type()),
+                |window, _, parameter| async move {
+                    let id = parameter
+                        .and_then(|parameter| parameter.get::<String>())
+                        .expect("Collection ID parameter is not a string");
+                    if let Some(collection) = window.action_collection(&id) {
+                        window.export_collection(collection).await;
+                    }
+                },
+            );
+
+            klass.install_action_async("win.export-all", None, |window, _, _| async move {
+                window.export_all().await;
+            });
+
+            klass.install_action_async(
+                "win.export-markdown",
+                Some(&String::static_variant_type()),
+                |window, _, parameter| async move {
+                    let id = parameter
+                        .and_then(|parameter| parameter.get::<String>())
+                        .expect("Collection ID parameter is not a string");
+                    if let Some(collection) = window.action_collection(&id) {
+                        window.export_markdown(collection).await;
+                    }
+                },
+            );
+
+            klass.install_action(
+                "win.copy-markdown",
+                Some(&String::static_variant_type()),
+                |window, _, parameter| {
+                    let id = parameter
+                        .and_then(String::from_variant)
+                        .expect("Collection ID parameter is not a string");
+                    if let Some(collection) = window.action_collection(&id) {
+                        window.copy_markdown(&collection);
+                    }
+                },
+            );
+
+            klass.install_action_async("win.import-todotxt", None, |window, _, _| async move {
+                window.import_todotxt().await;
+            });
+
+            klass.install_action_async("win.export-todotxt", None, |window, _, _| async move {
+                window.export_todotxt().await;
+            });
+
+            klass.install_action(
+                "win.delete-task",
+                Some(&String::static_variant_type()),
+                |window, _, parameter| {
+                    let id = parameter
+                        .and_then(String::from_variant)
+                        .expect("Task ID parameter is not a string");
+                    window.delete_task(&id);
+                },
+            );
+
+            klass.install_action(
+                "win.cycle-task-priority",
+                Some(&String::static_variant_type()),
+                |window, _, parameter| {
+                    let id = parameter
+                        .and_then(String::from_variant)
+                        .expect("Task ID parameter is not a string");
+                    if let Some(task) = window.find_task(&id) {
+                        window.cycle_task_priority(&task);
+                    }
+                },
+            );
+
+            klass.install_action(
+                "win.show-task-details",
+                Some(&String::static_variant_type()),
+                |window, _, parameter| {
+                    let id = parameter
+                        .and_then(String::from_variant)
+                        .expect("Task ID parameter is not a string");
+                    if let Some(task) = window.find_task(&id) {
+                        window.show_task_details(&task);
+                    }
+                },
+            );
+
+            klass.install_action(
+                "win.select-collection",
+                Some(&String::static_variant_type()),
+                |window, _, parameter| {
+                    let id = parameter
+                        .and_then(String::from_variant)
+                        .expect("Collection ID parameter is not a string");
+                    if let Some(collection) = window.find_collection(&id) {
+                        window.set_current_collection(collection);
+                        window.imp().split_view.set_show_content(true);
+                    }
+                },
+            );
+
+            klass.install_action(
+                "win.select-smart-collection",
+                Some(&String::static_variant_type()),
+                |window, _, parameter| {
+                    let id = parameter
+                        .and_then(String::from_variant)
+                        .expect("Smart collection ID parameter is not a string");
+                    if let Some(smart) = SmartCollection::from_id(&id) {
+                        window.set_current_collection(smart);
+                        window.imp().split_view.set_show_content(true);
+                    }
+                },
+            );
+
+            klass.install_action_async("win.new-saved-filter", None, |window, _, _| async move {
+                window.new_saved_filter().await;
+            });
+
+            klass.install_action_async(
+                "win.edit-saved-filter",
+                Some(&String::static_variant_type()),
+                |window, _, parameter| async move {
+                    let id = parameter
+                        .and_then(|parameter| parameter.get::<String>())
+                        .expect("Filter ID parameter is not a string");
+                    if let Some(saved_filter) = window.find_saved_filter(&id) {
+                        window.edit_saved_filter(saved_filter).await;
+                    }
+                },
+            );
+
+            klass.install_action(
+                "win.delete-saved-filter",
+                Some(&String::static_variant_type()),
+                |window, _, parameter| {
+                    let id = parameter
+                        .and_then(String::from_variant)
+                        .expect("Filter ID parameter is not a string");
+                    if let Some(saved_filter) = window.find_saved_filter(&id) {
+                        window.delete_saved_filter(saved_filter);
+                    }
+                },
+            );
+
+            klass.install_action(
+                "win.select-saved-filter",
+                Some(&String::static_variant_type()),
+                |window, _, parameter| {
+                    let id = parameter
+                        .and_then(String::from_variant)
+                        .expect("Filter ID parameter is not a string");
+                    if let Some(saved_filter) = window.find_saved_filter(&id) {
+                        window.set_current_collection(saved_filter);
+                        window.imp().split_view.set_show_content(true);
+                    }
+                },
+            );
+
+            klass.install_action(
+                "win.select-tag",
+                Some(&String::static_variant_type()),
+                |window, _, parameter| {
+                    let tag = parameter
+                        .and_then(String::from_variant)
+                        .expect("Tag parameter is not a string");
+                    window.select_tag(tag);
+                },
+            );
+
+            klass.install_action(
+                "win.open-backup",
+                Some(&String::static_variant_type()),
+                |window, _, parameter| {
+                    let path = parameter
+                        .and_then(String::from_variant)
+                        .expect("Backup path parameter is not a string");
+                    window.open_backup(Path::new(&path));
+                },
+            );
         }
 
         fn instance_init(obj: &subclass::InitializingObject<Self>) {
@@ -66,10 +338,32 @@ pub mod imp {
             // Setup
             let obj = self.obj();
             obj.setup_settings();
+            obj.setup_search();
             obj.setup_collections();
-            obj.restore_data();
+            obj.setup_saved_filters();
+            let restored = obj.restore_data();
+            obj.setup_autosave();
+            obj.setup_tags();
+            obj.setup_smart_collections();
+            obj.setup_reminders();
             obj.setup_callbacks();
             obj.setup_actions();
+
+            if let Err(err) = restored {
+                obj.recover_data_file(&err);
+            }
+        }
+
+        fn dispose(&self) {
+            for menu in [
+                &self.task_menu,
+                &self.collection_menu,
+                &self.saved_filter_menu,
+            ] {
+                if let Some(menu) = menu.get() {
+                    menu.unparent();
+                }
+            }
         }
     }
 
@@ -77,16 +371,19 @@ pub mod imp {
 
     impl WindowImpl for Window {
         fn close_request(&self) -> glib::Propagation {
-            let user_data = self
-                .obj()
-                .collections()
-                .iter::<CollectionObject>()
-                .filter_map(Result::ok)
-                .map(|task_object| task_object.to_collection_data())
-                .collect::<Vec<CollectionData>>();
-
-            let file = std::fs::File::create(data_path()).expect("Could not create file");
-            serde_json::to_writer(file, &user_data).expect("Could not write to file");
+            if !self.close_without_saving.get() {
+                if let Err(err) = self.obj().autosave().flush() {
+                    let obj = self.obj();
+                    glib::spawn_future_local(clone!(
+                        #[weak]
+                        obj,
+                        async move {
+                            obj.confirm_close_without_saving(&err).await;
+                        }
+                    ));
+                    return glib::Propagation::Stop;
+                }
+            }
 
             self.parent_close_request()
         }
@@ -108,36 +405,209 @@ impl Window {
         Object::builder().property("application", app).build()
     }
 
-    fn settings(&self) -> &gio::Settings {
-        self.imp().settings.get().expect("Settings were not setup.")
+    fn find_collection(&self, id: &str) -> Option<CollectionObject> {
+        self.collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .find(|collection| collection.id() == id)
+    }
+
+    fn find_saved_filter(&self, id: &str) -> Option<SavedFilterObject> {
+        self.saved_filters()
+            .iter::<SavedFilterObject>()
+            .filter_map(Result::ok)
+            .find(|saved_filter| saved_filter.id() == id)
+    }
+
+    /// Looks up a task in any collection by its ID.
+    pub fn find_task(&self, id: &str) -> Option<TaskObject> {
+        self.collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .find_map(|collection| collection.find_task(id))
+    }
+
+    /// Calls `callback` with a top-level task whenever it, or one of its
+    /// subtasks, is added, edited or removed.
+    pub fn connect_task_changed(&self, callback: impl Fn(&TaskObject) + 'static) {
+        self.autosave().connect_task_changed(callback);
+    }
+
+    pub fn snooze_task(&self, id: &str) {
+        self.reminders().snooze(id);
     }
 
-    fn tasks(&self) -> gio::ListStore {
-        self.current_collection().tasks()
+    fn settings(&self) -> &gio::Settings {
+        self.imp().settings.get().expect("Settings were not setup.")
     }
 
     fn new_task(&self) {
         let buffer = self.imp().entry.buffer();
-        let content = buffer.text().to_string();
-        if content.is_empty() {
+        let text = buffer.text().to_string();
+        if text.trim().is_empty() {
             return;
         }
+        let Some(collection) = self.new_task_collection() else {
+            return;
+        };
         buffer.set_text("");
 
-        let task = TaskObject::new(false, content);
-        self.tasks().append(&task);
+        collection.tasks().append(&self.quick_add_task(text, false));
     }
 
-    fn create_task_row(&self, task: &TaskObject) -> adw::ActionRow {
+    /// Adds a task for every item of a pasted Markdown checklist, returning
+    /// false when `text` is something else that should just be pasted.
+    fn paste_checklist(&self, text: &str) -> bool {
+        // A single line reads just as well as a task of its own
+        if !text.contains('\n') {
+            return false;
+        }
+        let (Some(items), Some(collection)) =
+            (markdown::parse_checklist(text), self.new_task_collection())
+        else {
+            return false;
+        };
+
+        let tasks = items
+            .into_iter()
+            .map(|(completed, content)| self.quick_add_task(content, completed))
+            .collect::<Vec<_>>();
+        collection.tasks().extend_from_slice(&tasks);
+        true
+    }
+
+    /// A task for `text` read like the entry reads it.
+    fn quick_add_task(&self, text: String, completed: bool) -> TaskObject {
+        let mut quick_add = Self::parse_quick_add(&text);
+        // A task added to a smart collection should show up in it
+        if quick_add.due_date.is_none() {
+            if let CollectionSource::Smart(smart) = self.current_source() {
+                let now = glib::DateTime::now_local().expect("Could not get the local time");
+                let (today, _) = split_date_time(&now);
+                quick_add.due_date = smart.default_due_date(today);
+            }
+        }
+
+        TaskObject::from_quick_add(text, quick_add, completed)
+    }
+
+    fn parse_quick_add(text: &str) -> QuickAdd {
+        let now = glib::DateTime::now_local().expect("Could not get the local time");
+        let (today, now) = split_date_time(&now);
+        quick_add::parse(text, today, now)
+    }
+
+    /// Shows what the entry text will turn into, as a row of chips.
+    fn update_quick_add_preview(&self) {
+        let imp = self.imp();
+        let preview = &imp.quick_add_preview;
+        while let Some(child) = preview.first_child() {
+            preview.remove(&child);
+        }
+
+        let quick_add = Self::parse_quick_add(&imp.entry.text());
+        let due = quick_add
+            .due_date
+            .and_then(|date| format_due(date, quick_add.due_time));
+        let priority = (quick_add.priority != Priority::None).then(|| quick_add.priority.label());
+        let recurrence = quick_add.recurrence.map(|rule| rule.to_string());
+        let tags = quick_add.tags.iter().map(|tag| format!("#{tag}"));
+
+        for chip in due
+            .into_iter()
+            .chain(recurrence)
+            .chain(priority.map(str::to_owned))
+            .chain(tags)
+        {
+            let label = gtk::Label::new(Some(&chip));
+            label.add_css_class("chip");
+            label.add_css_class("caption");
+            preview.append(&label);
+        }
+        preview.set_visible(preview.first_child().is_some());
+    }
+
+    fn create_task_row(&self, task: &TaskObject) -> adw::ExpanderRow {
         let check_button = gtk::CheckButton::builder()
             .valign(gtk::Align::Center)
             .can_focus(false)
             .build();
 
-        let row = adw::ActionRow::builder()
-            .activatable_widget(&check_button)
+        let priority_label = gtk::Label::builder().valign(gtk::Align::Center).build();
+        priority_label.add_css_class("priority");
+
+        let tags_box = gtk::Box::builder()
+            .spacing(4)
+            .valign(gtk::Align::Center)
+            .build();
+
+        let progress_label = gtk::Label::builder().valign(gtk::Align::Center).build();
+        progress_label.add_css_class("dim-label");
+        progress_label.add_css_class("caption");
+
+        let due_label = gtk::Label::builder().valign(gtk::Align::Center).build();
+        due_label.add_css_class("due-label");
+        due_label.add_css_class("caption");
+
+        let notes_icon = gtk::Image::builder()
+            .icon_name("text-x-generic-symbolic")
+            .tooltip_text("Has Notes")
+            .build();
+        notes_icon.add_css_class("dim-label");
+        task.bind_property("notes", &notes_icon, "visible")
+            .transform_to(|_, notes: String| Some(!notes.is_empty()))
+            .sync_create()
+            .build();
+
+        let recurrence_icon = gtk::Image::from_icon_name("media-playlist-repeat-symbolic");
+        recurrence_icon.add_css_class("dim-label");
+        task.bind_property("recurrence", &recurrence_icon, "tooltip-text")
+            .sync_create()
+            .build();
+        task.bind_property("recurrence", &recurrence_icon, "visible")
+            .transform_to(|_, recurrence: Option<String>| Some(recurrence.is_some()))
+            .sync_create()
+            .build();
+
+        let due_button = gtk::MenuButton::builder()
+            .icon_name("x-office-calendar-symbolic")
+            .tooltip_text("Due Date")
+            .valign(gtk::Align::Center)
+            .build();
+        due_button.add_css_class("flat");
+        due_button.set_create_popup_func(clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            task,
+            move |button| {
+                button.set_popover(Some(&window.create_due_popover(&task)));
+            }
+        ));
+
+        let delete_button = gtk::Button::builder()
+            .icon_name("user-trash-symbolic")
+            .tooltip_text("Delete Task")
+            .valign(gtk::Align::Center)
+            .action_name("win.delete-task")
+            .action_target(&task.id().to_variant())
             .build();
+        delete_button.add_css_class("flat");
+
+        let drag_handle = gtk::Image::from_icon_name("list-drag-handle-symbolic");
+        drag_handle.add_css_class("dim-label");
+
+        let row = adw::ExpanderRow::builder().use_markup(false).build();
+        row.add_prefix(&drag_handle);
         row.add_prefix(&check_button);
+        row.add_prefix(&priority_label);
+        row.add_suffix(&tags_box);
+        row.add_suffix(&progress_label);
+        row.add_suffix(&notes_icon);
+        row.add_suffix(&recurrence_icon);
+        row.add_suffix(&due_label);
+        row.add_suffix(&due_button);
+        row.add_suffix(&delete_button);
 
         task.bind_property("completed", &check_button, "active")
             .bidirectional()
@@ -148,174 +618,2152 @@ impl Window {
             .sync_create()
             .build();
 
+        // The expanded part holds the content editor, a link to the notes,
+        // the subtasks and an entry for new subtasks, in that order
+        let content_row = self.create_content_row(task);
+        row.add_row(&content_row);
+        row.add_row(&Self::create_notes_row(task));
+        let add_subtask_row = self.create_add_subtask_row(task);
+        row.add_row(&add_subtask_row);
+
+        let subtask_rows = Rc::new(RefCell::new(Vec::new()));
+        let sync_subtask_rows = clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            task,
+            #[weak]
+            row,
+            #[weak]
+            add_subtask_row,
+            #[weak]
+            progress_label,
+            #[strong]
+            subtask_rows,
+            move || {
+                for subtask_row in subtask_rows.take() {
+                    row.remove(&subtask_row);
+                }
+                row.remove(&add_subtask_row);
+
+                for subtask in task.subtasks().iter::<TaskObject>().filter_map(Result::ok) {
+                    let subtask_row = window.create_subtask_row(&task, &subtask, &progress_label);
+                    row.add_row(&subtask_row);
+                    subtask_rows.borrow_mut().push(subtask_row);
+                }
+                row.add_row(&add_subtask_row);
+
+                Self::update_task_row_progress(&progress_label, &task);
+            }
+        );
+        sync_subtask_rows();
+        let subtasks = task.subtasks();
+        let handler_id = subtasks.connect_items_changed(move |_, _, _, _| sync_subtask_rows());
+        Self::disconnect_on_destroy(&row, &subtasks, handler_id);
+
+        // F2 opens the content editor, like renaming a file, Ctrl+P cycles the priority
+        let key_controller = gtk::EventControllerKey::new();
+        key_controller.connect_key_pressed(clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            task,
+            #[weak]
+            row,
+            #[weak]
+            content_row,
+            #[upgrade_or]
+            glib::Propagation::Proceed,
+            move |_, key, _, modifiers| {
+                let modifiers = modifiers & gtk::accelerator_get_default_mod_mask();
+                if key == gdk::Key::F2 {
+                    row.set_expanded(true);
+                    content_row.grab_focus();
+                } else if key == gdk::Key::p && modifiers == gdk::ModifierType::CONTROL_MASK {
+                    window.cycle_task_priority(&task);
+                } else {
+                    return glib::Propagation::Proceed;
+                }
+                glib::Propagation::Stop
+            }
+        ));
+        row.add_controller(key_controller);
+
+        // Dragging starts from the handle, so it doesn't fight with the expander
+        drag_handle.add_controller(Self::create_drag_source(row.upcast_ref(), &task.to_value()));
+
+        let drop_target = gtk::DropTarget::new(TaskObject::static_type(), gdk::DragAction::MOVE);
+        drop_target.connect_drop(clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            task,
+            #[upgrade_or]
+            false,
+            move |_, value, _, _| {
+                let Ok(dropped) = value.get::<TaskObject>() else {
+                    return false;
+                };
+                // Smart collections mix tasks, so look up where the target really is
+                let Some((collection, _, position)) = window.locate_task(&task.id()) else {
+                    return false;
+                };
+                dropped != task && window.move_task(&dropped, &collection, Some(position))
+            }
+        ));
+        row.add_controller(drop_target);
+
+        Self::update_task_row_tags(&tags_box, task);
+        let handler_id = task.connect_tags_notify(clone!(
+            #[weak]
+            tags_box,
+            move |task| {
+                Self::update_task_row_tags(&tags_box, task);
+            }
+        ));
+        Self::disconnect_on_destroy(&row, task, handler_id);
+
+        Self::update_task_row_priority(&priority_label, task);
+        let handler_id = task.connect_priority_notify(clone!(
+            #[weak]
+            priority_label,
+            move |task| {
+                Self::update_task_row_priority(&priority_label, task);
+            }
+        ));
+        Self::disconnect_on_destroy(&row, task, handler_id);
+
+        // Any edit may move the task under the current sort mode
+        let handler_id = task.connect_modified_at_notify(clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| {
+                window.resort();
+            }
+        ));
+        Self::disconnect_on_destroy(&row, task, handler_id);
+
+        Self::update_task_row_due(&row, &due_label, task);
+        for property in ["completed", "due-date", "due-time"] {
+            let handler_id = task.connect_notify_local(
+                Some(property),
+                clone!(
+                    #[weak]
+                    row,
+                    #[weak]
+                    due_label,
+                    move |task, _| {
+                        Self::update_task_row_due(&row, &due_label, task);
+                    }
+                ),
+            );
+            Self::disconnect_on_destroy(&row, task, handler_id);
+        }
+
+        row
+    }
+
+    /// Disconnects a handler on a long-lived model object once the row
+    /// showing it goes away.
+    fn disconnect_on_destroy(
+        widget: &impl IsA<gtk::Widget>,
+        object: &impl IsA<glib::Object>,
+        handler_id: glib::SignalHandlerId,
+    ) {
+        let object = object.upcast_ref::<glib::Object>().downgrade();
+        let handler_id = RefCell::new(Some(handler_id));
+        widget.connect_destroy(move |_| {
+            if let (Some(object), Some(handler_id)) = (object.upgrade(), handler_id.take()) {
+                object.disconnect(handler_id);
+            }
+        });
+    }
+
+    /// An entry to edit the task content. Enter commits, Escape cancels.
+    fn create_content_row(&self, task: &TaskObject) -> adw::EntryRow {
+        let row = adw::EntryRow::builder().title("Task").build();
+
+        // Edits only reach the task once they are committed with Enter
+        task.bind_property("content", &row, "text")
+            .sync_create()
+            .build();
+
+        row.connect_entry_activated(clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            task,
+            move |row| {
+                glib::spawn_future_local(clone!(
+                    #[weak]
+                    window,
+                    #[weak]
+                    task,
+                    #[weak]
+                    row,
+                    async move {
+                        window.commit_task_content(&row, &task).await;
+                    }
+                ));
+            }
+        ));
+
+        let key_controller = gtk::EventControllerKey::new();
+        key_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        key_controller.connect_key_pressed(clone!(
+            #[weak]
+            task,
+            #[weak]
+            row,
+            #[upgrade_or]
+            glib::Propagation::Proceed,
+            move |_, key, _, _| {
+                if key != gdk::Key::Escape {
+                    return glib::Propagation::Proceed;
+                }
+                row.set_text(&task.content());
+                glib::Propagation::Stop
+            }
+        ));
+        row.add_controller(key_controller);
+
         row
     }
 
-    fn remove_done_tasks(&self) {
-        self.tasks()
-            .retain(|x| x.downcast_ref::<TaskObject>().unwrap().is_completed() != true);
+    /// Shows the first line of the notes and opens all of them on activation.
+    fn create_notes_row(task: &TaskObject) -> adw::ActionRow {
+        let row = adw::ActionRow::builder()
+            .title("Notes")
+            .subtitle_lines(1)
+            .use_markup(false)
+            .activatable(true)
+            .action_name("win.show-task-details")
+            .action_target(&task.id().to_variant())
+            .build();
+        row.add_suffix(&gtk::Image::from_icon_name("go-next-symbolic"));
+
+        task.bind_property("notes", &row, "subtitle")
+            .sync_create()
+            .build();
+
+        row
     }
 
-    async fn new_collection(&self) {
-        let entry = gtk::Entry::builder()
-            .placeholder_text("Name")
-            .activates_default(true)
+    /// A dialog to edit the notes of `task`, saved to the task once it is closed.
+    fn show_task_details(&self, task: &TaskObject) {
+        let buffer = gtk::TextBuffer::new(None);
+        Self::create_markdown_tags(&buffer);
+        buffer.connect_changed(Self::highlight_markdown);
+        buffer.set_text(&task.notes());
+
+        let text_view = gtk::TextView::builder()
+            .buffer(&buffer)
+            .wrap_mode(gtk::WrapMode::WordChar)
+            .top_margin(12)
+            .bottom_margin(12)
+            .left_margin(12)
+            .right_margin(12)
             .build();
 
-        let cancel_response = "cancel";
-        let create_response = "create";
+        let scrolled_window = gtk::ScrolledWindow::builder()
+            .child(&text_view)
+            .vexpand(true)
+            .build();
 
-        let dialog = adw::AlertDialog::builder()
-            .heading("New Collection")
-            .close_response(cancel_response)
-            .default_response(create_response)
-            .extra_child(&entry)
+        let toolbar_view = adw::ToolbarView::builder()
+            .content(&scrolled_window)
             .build();
-        dialog.add_responses(&[(cancel_response, "Cancel"), (create_response, "Create")]);
+        toolbar_view.add_top_bar(&adw::HeaderBar::new());
 
-        dialog.set_response_enabled(create_response, false);
-        dialog.set_response_appearance(create_response, adw::ResponseAppearance::Suggested);
+        let dialog = adw::Dialog::builder()
+            .child(&toolbar_view)
+            .content_width(480)
+            .content_height(400)
+            .build();
+        task.bind_property("content", &dialog, "title")
+            .sync_create()
+            .build();
 
-        entry.connect_changed(clone!(
+        dialog.connect_closed(clone!(
             #[weak]
-            dialog,
-            move |entry| {
-                let text = entry.text();
-                let empty = text.is_empty();
+            task,
+            #[weak]
+            buffer,
+            move |_| {
+                let (start, end) = buffer.bounds();
+                let notes = buffer.text(&start, &end, false);
+                if notes != task.notes() {
+                    task.set_notes(notes.as_str());
+                }
+            }
+        ));
+
+        dialog.present(Some(self));
+        text_view.grab_focus();
+    }
 
-                dialog.set_response_enabled(create_response, !empty);
+    /// One tag per `markdown::Style`, named like `markdown_tag_name` returns.
+    fn create_markdown_tags(buffer: &gtk::TextBuffer) {
+        let tags = [
+            ("heading-1", 1.6, 700, pango::Style::Normal),
+            ("heading-2", 1.4, 700, pango::Style::Normal),
+            ("heading-3", 1.2, 700, pango::Style::Normal),
+            ("heading-4", 1.0, 700, pango::Style::Normal),
+            ("heading-5", 1.0, 700, pango::Style::Normal),
+            ("heading-6", 1.0, 700, pango::Style::Normal),
+            ("list-marker", 1.0, 700, pango::Style::Normal),
+            ("bold", 1.0, 700, pango::Style::Normal),
+            ("italic", 1.0, 400, pango::Style::Italic),
+        ];
+        for (name, scale, weight, style) in tags {
+            buffer.create_tag(
+                Some(name),
+                &[("scale", &scale), ("weight", &weight), ("style", &style)],
+            );
+        }
+        buffer.create_tag(Some("strikethrough"), &[("strikethrough", &true)]);
+        buffer.create_tag(Some("code"), &[("family", &"monospace")]);
+    }
 
-                if empty {
-                    entry.add_css_class("error");
-                } else {
-                    entry.remove_css_class("error");
+    fn markdown_tag_name(style: Style) -> &'static str {
+        match style {
+            Style::Heading(1) => "heading-1",
+            Style::Heading(2) => "heading-2",
+            Style::Heading(3) => "heading-3",
+            Style::Heading(4) => "heading-4",
+            Style::Heading(5) => "heading-5",
+            Style::Heading(_) => "heading-6",
+            Style::ListMarker => "list-marker",
+            Style::Bold => "bold",
+            Style::Italic => "italic",
+            Style::Strikethrough => "strikethrough",
+            Style::Code => "code",
+        }
+    }
+
+    /// Restyles the whole buffer on every change, notes are short enough for that.
+    fn highlight_markdown(buffer: &gtk::TextBuffer) {
+        let (start, end) = buffer.bounds();
+        buffer.remove_all_tags(&start, &end);
+
+        let text = buffer.text(&start, &end, false);
+        for span in markdown::highlight(&text) {
+            let start = buffer.iter_at_offset(span.start as i32);
+            let end = buffer.iter_at_offset(span.end as i32);
+            buffer.apply_tag_by_name(Self::markdown_tag_name(span.style), &start, &end);
+        }
+    }
+
+    fn create_subtask_row(
+        &self,
+        task: &TaskObject,
+        subtask: &TaskObject,
+        progress_label: &gtk::Label,
+    ) -> adw::ActionRow {
+        let check_button = gtk::CheckButton::builder()
+            .valign(gtk::Align::Center)
+            .can_focus(false)
+            .build();
+
+        let delete_button = gtk::Button::builder()
+            .icon_name("edit-delete-symbolic")
+            .tooltip_text("Delete Step")
+            .valign(gtk::Align::Center)
+            .build();
+        delete_button.add_css_class("flat");
+
+        let row = adw::ActionRow::builder()
+            .activatable_widget(&check_button)
+            .use_markup(false)
+            .build();
+        row.add_prefix(&check_button);
+        row.add_suffix(&delete_button);
+
+        subtask
+            .bind_property("completed", &check_button, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        subtask
+            .bind_property("content", &row, "title")
+            .sync_create()
+            .build();
+
+        check_button.connect_toggled(clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            task,
+            #[weak]
+            progress_label,
+            move |check_button| {
+                Self::update_task_row_progress(&progress_label, &task);
+                if check_button.is_active() {
+                    window.complete_if_subtasks_done(&task);
                 }
             }
         ));
 
-        let response = dialog.choose_future(self).await;
+        delete_button.connect_clicked(clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            subtask,
+            move |_| {
+                window.delete_task(&subtask.id());
+            }
+        ));
 
-        if response == cancel_response {
-            return;
-        }
+        row
+    }
 
-        let tasks = gio::ListStore::new::<TaskObject>();
+    fn create_add_subtask_row(&self, task: &TaskObject) -> adw::EntryRow {
+        let row = adw::EntryRow::builder().title("Add Step").build();
 
-        let title = entry.text().to_string();
-        let collection = CollectionObject::new(&title, tasks);
+        row.connect_entry_activated(clone!(
+            #[weak]
+            task,
+            move |row| {
+                let content = row.text();
+                if content.is_empty() {
+                    return;
+                }
+                row.set_text("");
+                task.subtasks()
+                    .append(&TaskObject::new(false, content.to_string()));
+            }
+        ));
 
-        self.collections().append(&collection);
-        self.set_current_collection(collection);
+        row
+    }
+
+    fn update_task_row_tags(tags_box: &gtk::Box, task: &TaskObject) {
+        while let Some(child) = tags_box.first_child() {
+            tags_box.remove(&child);
+        }
+        for tag in task.tags() {
+            let label = gtk::Label::new(Some(&format!("#{tag}")));
+            label.add_css_class("tag");
+            label.add_css_class("caption");
+            tags_box.append(&label);
+        }
+    }
+
+    fn update_task_row_priority(priority_label: &gtk::Label, task: &TaskObject) {
+        let priority = task.priority();
+        for class in priority_label.css_classes() {
+            if class.starts_with("priority-") {
+                priority_label.remove_css_class(&class);
+            }
+        }
+        if let Some(class) = priority.css_class() {
+            priority_label.add_css_class(class);
+        }
+
+        // One mark per level, like "!!" for medium
+        priority_label.set_visible(priority != Priority::None);
+        priority_label.set_label(&"!".repeat(priority as usize));
+        priority_label.set_tooltip_text(Some(priority.label()));
+    }
+
+    fn cycle_task_priority(&self, task: &TaskObject) {
+        task.set_priority(task.priority().next());
+    }
+
+    fn update_task_row_progress(progress_label: &gtk::Label, task: &TaskObject) {
+        let (completed, total) = task.subtask_progress();
+        progress_label.set_visible(total > 0);
+        progress_label.set_label(&format!("{completed}/{total}"));
+    }
+
+    /// Completes `task` once all its subtasks are, if the user opted in.
+    fn complete_if_subtasks_done(&self, task: &TaskObject) {
+        if !self.settings().boolean("complete-parent-with-subtasks") {
+            return;
+        }
+        let (completed, total) = task.subtask_progress();
+        if total > 0 && completed == total {
+            task.set_completed(true);
+        }
+    }
+
+    fn update_task_row_due(row: &adw::ExpanderRow, due_label: &gtk::Label, task: &TaskObject) {
+        let label = task.due_label();
+        due_label.set_visible(label.is_some());
+        due_label.set_label(&label.unwrap_or_default());
+
+        let overdue = glib::DateTime::now_local().is_ok_and(|now| task.is_overdue(&now));
+        if overdue {
+            row.add_css_class("overdue");
+        } else {
+            row.remove_css_class("overdue");
+        }
+    }
+
+    async fn commit_task_content(&self, row: &adw::EntryRow, task: &TaskObject) {
+        let content = row.text();
+        if !content.is_empty() {
+            if content.as_str() != task.content() {
+                task.set_content(content.as_str());
+            }
+            return;
+        }
+
+        if self.confirm_remove_empty_task().await {
+            self.delete_task(&task.id());
+        } else {
+            row.set_text(&task.content());
+        }
+    }
+
+    async fn confirm_remove_empty_task(&self) -> bool {
+        let cancel_response = "cancel";
+        let delete_response = "delete";
+
+        let dialog = adw::AlertDialog::builder()
+            .heading("Delete Task?")
+            .body("The task has no content left.")
+            .close_response(cancel_response)
+            .default_response(cancel_response)
+            .build();
+        dialog.add_responses(&[(cancel_response, "Cancel"), (delete_response, "Delete")]);
+        dialog.set_response_appearance(delete_response, adw::ResponseAppearance::Destructive);
+
+        dialog.choose_future(self).await == delete_response
+    }
+
+    fn create_due_popover(&self, task: &TaskObject) -> gtk::Popover {
+        let calendar = gtk::Calendar::new();
+
+        let time_entry = gtk::Entry::builder()
+            .placeholder_text("Time (HH:MM, optional)")
+            .build();
+
+        let recurrence_entry = gtk::Entry::builder()
+            .placeholder_text("Repeat, like “every week” (optional)")
+            .build();
+
+        let clear_button = gtk::Button::with_label("Clear");
+        let set_button = gtk::Button::with_label("Set");
+        set_button.add_css_class("suggested-action");
+
+        let buttons = gtk::Box::builder().spacing(6).homogeneous(true).build();
+        buttons.append(&clear_button);
+        buttons.append(&set_button);
+
+        let content = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .build();
+        content.append(&calendar);
+        content.append(&time_entry);
+        content.append(&recurrence_entry);
+        content.append(&buttons);
+
+        let popover = gtk::Popover::builder().child(&content).build();
+
+        popover.connect_show(clone!(
+            #[weak]
+            task,
+            #[weak]
+            calendar,
+            #[weak]
+            time_entry,
+            #[weak]
+            recurrence_entry,
+            move |_| {
+                let (date, time) = task.due().unzip();
+                if let Some(date_time) = date.and_then(|date| local_date_time(date, None)) {
+                    calendar.select_day(&date_time);
+                }
+                let time = time.flatten().map(|time| time.to_string());
+                time_entry.set_text(&time.unwrap_or_default());
+                recurrence_entry.set_text(&task.recurrence().unwrap_or_default());
+            }
+        ));
+
+        let validate = clone!(
+            #[weak]
+            time_entry,
+            #[weak]
+            recurrence_entry,
+            #[weak]
+            set_button,
+            move || {
+                let time = time_entry.text();
+                let time_valid = time.is_empty() || Time::parse(&time).is_some();
+                // The anchor doesn't matter for telling whether it parses
+                let recurrence = recurrence_entry.text();
+                let recurrence_valid =
+                    recurrence.is_empty() || recurrence.parse::<Recurrence>().is_ok();
+
+                set_button.set_sensitive(time_valid && recurrence_valid);
+
+                for (entry, valid) in [
+                    (&time_entry, time_valid),
+                    (&recurrence_entry, recurrence_valid),
+                ] {
+                    if valid {
+                        entry.remove_css_class("error");
+                    } else {
+                        entry.add_css_class("error");
+                    }
+                }
+            }
+        );
+
+        for entry in [&time_entry, &recurrence_entry] {
+            let validate = validate.clone();
+            entry.connect_changed(move |_| validate());
+
+            entry.connect_activate(clone!(
+                #[weak]
+                set_button,
+                move |_| {
+                    if set_button.is_sensitive() {
+                        set_button.emit_clicked();
+                    }
+                }
+            ));
+        }
+
+        set_button.connect_clicked(clone!(
+            #[weak]
+            task,
+            #[weak]
+            calendar,
+            #[weak]
+            time_entry,
+            #[weak]
+            recurrence_entry,
+            #[weak]
+            popover,
+            move |_| {
+                let (date, _) = split_date_time(&calendar.date());
+                let time = Time::parse(&time_entry.text());
+                let recurrence = recurrence::parse(&recurrence_entry.text(), date);
+
+                task.set_due_date(Some(date.to_string()));
+                task.set_due_time(time.map(|time| time.to_string()));
+                task.set_recurrence_rule(recurrence);
+                popover.popdown();
+            }
+        ));
+
+        clear_button.connect_clicked(clone!(
+            #[weak]
+            task,
+            #[weak]
+            popover,
+            move |_| {
+                task.set_due_date(None::<String>);
+                task.set_due_time(None::<String>);
+                task.set_recurrence_rule(None);
+                popover.popdown();
+            }
+        ));
+
+        popover
+    }
+
+    /// Deletes the task or subtask with `id`, with a toast to undo it.
+    fn delete_task(&self, id: &str) {
+        let Some((collection, tasks, position)) = self.locate_task(id) else {
+            return;
+        };
+        let Some(task) = tasks.item(position).and_downcast::<TaskObject>() else {
+            return;
+        };
+
+        tasks.remove(position);
+        let title = if tasks == collection.tasks() {
+            "Task deleted"
+        } else {
+            "Step deleted"
+        };
+        self.show_undo_toast(title, vec![(tasks, position, task)]);
+    }
+
+    /// Removes the done tasks that are shown, which for a smart collection or
+    /// a saved filter may come from any collection.
+    fn remove_done_tasks(&self) {
+        let source_filter = self.imp().current_source_filter.borrow().clone();
+        let collections = match self.current_collection() {
+            Some(collection) => vec![collection],
+            None => self
+                .collections()
+                .iter::<CollectionObject>()
+                .filter_map(Result::ok)
+                .collect(),
+        };
+        let is_removed = |task: &TaskObject| {
+            task.is_completed()
+                && source_filter
+                    .as_ref()
+                    .is_none_or(|source_filter| source_filter.match_(task))
+        };
+
+        let mut removed = Vec::new();
+        for collection in collections {
+            let tasks = collection.tasks();
+            removed.extend(
+                tasks
+                    .iter::<TaskObject>()
+                    .filter_map(Result::ok)
+                    .enumerate()
+                    .filter(|(_, task)| is_removed(task))
+                    .map(|(position, task)| (tasks.clone(), position as u32, task)),
+            );
+            tasks.retain(|x| !is_removed(x.downcast_ref::<TaskObject>().unwrap()));
+        }
+        if removed.is_empty() {
+            return;
+        }
+
+        let title = match removed.len() {
+            1 => "1 done task removed".to_owned(),
+            n => format!("{n} done tasks removed"),
+        };
+        self.show_undo_toast(&title, removed);
+    }
+
+    /// Shows a toast that puts `removed` tasks back into the stores they were
+    /// in, a collection's tasks or a task's subtasks. The positions must be
+    /// ascending per store, so each task lands exactly where it was.
+    fn show_undo_toast(&self, title: &str, removed: Vec<(gio::ListStore, u32, TaskObject)>) {
+        let toast = adw::Toast::builder()
+            .title(title)
+            .button_label("Undo")
+            .build();
+        toast.connect_button_clicked(move |_| {
+            for (tasks, position, task) in &removed {
+                tasks.insert((*position).min(tasks.n_items()), task);
+            }
+        });
+
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    /// The collection of the task with `id`, the store it is in, which is the
+    /// collection's tasks or its parent's subtasks, and its position there.
+    fn locate_task(&self, id: &str) -> Option<(CollectionObject, gio::ListStore, u32)> {
+        self.collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .find_map(|collection| {
+                let (parent, task) = collection
+                    .tasks_with_parents()
+                    .into_iter()
+                    .find(|(_, task)| task.id() == id)?;
+                let tasks = parent.map_or_else(|| collection.tasks(), |parent| parent.subtasks());
+                let position = tasks.find(&task)?;
+                Some((collection, tasks, position))
+            })
+    }
+
+    fn show_task_menu(&self, task: &TaskObject, x: f64, y: f64) {
+        let menu = gio::Menu::new();
+        let priority_item = gio::MenuItem::new(Some("Cycle _Priority"), None);
+        priority_item.set_action_and_target_value(
+            Some("win.cycle-task-priority"),
+            Some(&task.id().to_variant()),
+        );
+        menu.append_item(&priority_item);
+
+        let details_item = gio::MenuItem::new(Some("_Notes…"), None);
+        details_item.set_action_and_target_value(
+            Some("win.show-task-details"),
+            Some(&task.id().to_variant()),
+        );
+        menu.append_item(&details_item);
+
+        let delete_item = gio::MenuItem::new(Some("_Delete"), None);
+        delete_item
+            .set_action_and_target_value(Some("win.delete-task"), Some(&task.id().to_variant()));
+        menu.append_item(&delete_item);
+
+        let imp = self.imp();
+        Self::popup_menu(&imp.task_menu, &*imp.tasks_list, &menu, x, y);
+    }
+
+    fn show_saved_filter_menu(&self, id: &str, x: f64, y: f64) {
+        let menu = gio::Menu::new();
+        for (label, action) in [
+            ("_Edit…", "win.edit-saved-filter"),
+            ("_Delete", "win.delete-saved-filter"),
+        ] {
+            let item = gio::MenuItem::new(Some(label), None);
+            item.set_action_and_target_value(Some(action), Some(&id.to_variant()));
+            menu.append_item(&item);
+        }
+
+        let imp = self.imp();
+        Self::popup_menu(
+            &imp.saved_filter_menu,
+            &*imp.saved_filters_list,
+            &menu,
+            x,
+            y,
+        );
+    }
+
+    fn show_collection_menu(&self, id: &str, x: f64, y: f64) {
+        let menu = gio::Menu::new();
+        for (label, action) in [
+            ("_Rename…", "win.rename-collection"),
+            ("_Delete…", "win.delete-collection"),
+            ("_Export as iCalendar…", "win.export-collection"),
+            ("Export as _Markdown…", "win.export-markdown"),
+            ("_Copy as Markdown", "win.copy-markdown"),
+            ("Use for _New Tasks", "win.default-collection"),
+        ] {
+            let item = gio::MenuItem::new(Some(label), None);
+            item.set_action_and_target_value(Some(action), Some(&id.to_variant()));
+            menu.append_item(&item);
+        }
+
+        let imp = self.imp();
+        Self::popup_menu(&imp.collection_menu, &*imp.collections_list, &menu, x, y);
+    }
+
+    /// Pops up a context menu at `x`, `y` in `parent`, creating the popover on first use.
+    fn popup_menu(
+        popover: &OnceCell<gtk::PopoverMenu>,
+        parent: &impl IsA<gtk::Widget>,
+        menu: &gio::Menu,
+        x: f64,
+        y: f64,
+    ) {
+        let popover = popover.get_or_init(|| {
+            let popover = gtk::PopoverMenu::from_model(None::<&gio::MenuModel>);
+            popover.set_has_arrow(false);
+            popover.set_halign(gtk::Align::Start);
+            popover.set_parent(parent);
+            popover
+        });
+        popover.set_menu_model(Some(menu));
+        popover.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+        popover.popup();
+    }
+
+    async fn new_collection(&self) {
+        let Some(title) = self
+            .ask_collection_title("New Collection", "Create", "")
+            .await
+        else {
+            return;
+        };
+
+        let tasks = gio::ListStore::new::<TaskObject>();
+        let collection = CollectionObject::new(&title, tasks);
+
+        self.collections().append(&collection);
+        self.set_current_collection(collection);
+
+        self.imp().split_view.set_show_content(true);
+    }
+
+    async fn rename_collection(&self, collection: CollectionObject) {
+        let Some(title) = self
+            .ask_collection_title("Rename Collection", "Rename", &collection.title())
+            .await
+        else {
+            return;
+        };
+
+        collection.set_title(title);
+    }
+
+    async fn delete_collection(&self, collection: CollectionObject) {
+        let n_tasks = collection.tasks().n_items();
+
+        let cancel_response = "cancel";
+        let delete_response = "delete";
+
+        let dialog = adw::AlertDialog::builder()
+            .heading(format!("Delete “{}”?", collection.title()))
+            .body(match n_tasks {
+                0 => "The collection is empty.".to_owned(),
+                1 => "Its task will be deleted as well.".to_owned(),
+                n => format!("All of its {n} tasks will be deleted as well."),
+            })
+            .close_response(cancel_response)
+            .default_response(cancel_response)
+            .build();
+        dialog.add_responses(&[(cancel_response, "Cancel"), (delete_response, "Delete")]);
+        dialog.set_response_appearance(delete_response, adw::ResponseAppearance::Destructive);
+
+        let response = dialog.choose_future(self).await;
+
+        if response != delete_response {
+            return;
+        }
+
+        let collections = self.collections();
+        let Some(position) = collections.find(&collection) else {
+            return;
+        };
+        collections.remove(position);
+
+        let is_current = self.current_collection().as_ref() == Some(&collection);
+        if !is_current {
+            return;
+        }
+
+        // Prefer the collection that moved into the deleted one's place
+        let neighbour = collections
+            .item(position)
+            .or_else(|| collections.item(position.checked_sub(1)?))
+            .and_downcast::<CollectionObject>();
+        match neighbour {
+            Some(neighbour) => self.set_current_collection(neighbour),
+            None => self.clear_current_collection(),
+        }
+    }
+
+    /// Asks for a collection title, returning `None` if the dialog was cancelled.
+    async fn ask_collection_title(
+        &self,
+        heading: &str,
+        confirm_label: &str,
+        initial_title: &str,
+    ) -> Option<String> {
+        let entry = gtk::Entry::builder()
+            .placeholder_text("Name")
+            .text(initial_title)
+            .activates_default(true)
+            .build();
+
+        let cancel_response = "cancel";
+        let confirm_response = "confirm";
+
+        let dialog = adw::AlertDialog::builder()
+            .heading(heading)
+            .close_response(cancel_response)
+            .default_response(confirm_response)
+            .extra_child(&entry)
+            .build();
+        dialog.add_responses(&[
+            (cancel_response, "Cancel"),
+            (confirm_response, confirm_label),
+        ]);
+
+        dialog.set_response_enabled(confirm_response, !initial_title.is_empty());
+        dialog.set_response_appearance(confirm_response, adw::ResponseAppearance::Suggested);
+
+        entry.connect_changed(clone!(
+            #[weak]
+            dialog,
+            move |entry| {
+                let text = entry.text();
+                let empty = text.is_empty();
+
+                dialog.set_response_enabled(confirm_response, !empty);
+
+                if empty {
+                    entry.add_css_class("error");
+                } else {
+                    entry.remove_css_class("error");
+                }
+            }
+        ));
+
+        let response = dialog.choose_future(self).await;
+
+        if response == cancel_response {
+            return None;
+        }
+
+        Some(entry.text().to_string())
+    }
+
+    async fn new_saved_filter(&self) {
+        // Start from whatever is being searched for
+        let search = self.imp().search_entry.text();
+        let Some((name, query)) = self
+            .ask_saved_filter("New Filter", "Create", "", &search)
+            .await
+        else {
+            return;
+        };
+
+        let saved_filter = SavedFilterObject::new(&name, &query);
+        self.saved_filters().append(&saved_filter);
+        self.set_current_collection(saved_filter);
+
+        self.imp().split_view.set_show_content(true);
+    }
+
+    async fn edit_saved_filter(&self, saved_filter: SavedFilterObject) {
+        let Some((name, query)) = self
+            .ask_saved_filter(
+                "Edit Filter",
+                "Save",
+                &saved_filter.name(),
+                &saved_filter.query(),
+            )
+            .await
+        else {
+            return;
+        };
+
+        saved_filter.set_name(name);
+        saved_filter.set_query(query);
+
+        // The task list still runs the old query
+        let source = CollectionSource::SavedFilter(saved_filter);
+        let is_current = self.imp().current_collection.borrow().as_ref() == Some(&source);
+        if is_current {
+            self.set_current_collection(source);
+        }
+    }
+
+    fn delete_saved_filter(&self, saved_filter: SavedFilterObject) {
+        let saved_filters = self.saved_filters();
+        let Some(position) = saved_filters.find(&saved_filter) else {
+            return;
+        };
+        saved_filters.remove(position);
+
+        let source = CollectionSource::SavedFilter(saved_filter.clone());
+        let is_current = self.imp().current_collection.borrow().as_ref() == Some(&source);
+        if is_current {
+            match self
+                .collections()
+                .item(0)
+                .and_downcast::<CollectionObject>()
+            {
+                Some(collection) => self.set_current_collection(collection),
+                None => self.clear_current_collection(),
+            }
+        }
+
+        let toast = adw::Toast::builder()
+            .title("Filter deleted")
+            .button_label("Undo")
+            .build();
+        toast.connect_button_clicked(move |_| {
+            saved_filters.insert(position.min(saved_filters.n_items()), &saved_filter);
+        });
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    /// Asks for the name and query of a saved filter, returning `None` if the
+    /// dialog was cancelled. Only queries that parse can be confirmed.
+    async fn ask_saved_filter(
+        &self,
+        heading: &str,
+        confirm_label: &str,
+        initial_name: &str,
+        initial_query: &str,
+    ) -> Option<(String, String)> {
+        let name_entry = gtk::Entry::builder()
+            .placeholder_text("Name")
+            .text(initial_name)
+            .activates_default(true)
+            .build();
+        let query_entry = gtk::Entry::builder()
+            .placeholder_text("tag:work due:<7d -done")
+            .text(initial_query)
+            .activates_default(true)
+            .build();
+
+        let fields = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(12)
+            .build();
+        fields.append(&name_entry);
+        fields.append(&query_entry);
+
+        let cancel_response = "cancel";
+        let confirm_response = "confirm";
+
+        let dialog = adw::AlertDialog::builder()
+            .heading(heading)
+            .body(
+                "Shows the tasks of all collections that match every term: done, open, \
+                 tag:name, due:<7d, due:none, priority>=high or any word of the task. \
+                 Put a - in front of a term to leave its tasks out.",
+            )
+            .close_response(cancel_response)
+            .default_response(confirm_response)
+            .extra_child(&fields)
+            .build();
+        dialog.add_responses(&[
+            (cancel_response, "Cancel"),
+            (confirm_response, confirm_label),
+        ]);
+        dialog.set_response_appearance(confirm_response, adw::ResponseAppearance::Suggested);
+
+        let validate = Rc::new(clone!(
+            #[weak]
+            dialog,
+            #[weak]
+            name_entry,
+            #[weak]
+            query_entry,
+            move || {
+                let query = query_entry.text().parse::<Query>();
+                match &query {
+                    Ok(_) => {
+                        query_entry.remove_css_class("error");
+                        query_entry.set_tooltip_text(None);
+                    }
+                    Err(err) => {
+                        query_entry.add_css_class("error");
+                        query_entry.set_tooltip_text(Some(&err.to_string()));
+                    }
+                }
+
+                let valid = !name_entry.text().is_empty() && query.is_ok();
+                dialog.set_response_enabled(confirm_response, valid);
+            }
+        ));
+        validate();
+        for entry in [&name_entry, &query_entry] {
+            entry.connect_changed(clone!(
+                #[strong]
+                validate,
+                move |_| validate()
+            ));
+        }
+
+        let response = dialog.choose_future(self).await;
+
+        if response == cancel_response {
+            return None;
+        }
+
+        Some((
+            name_entry.text().to_string(),
+            query_entry.text().to_string(),
+        ))
+    }
+
+    /// Asks for an `.ics` file and adds its tasks to a collection.
+    async fn import_tasks(&self) {
+        let dialog = gtk::FileDialog::builder()
+            .title("Import Tasks")
+            .filters(&Self::calendar_filters())
+            .build();
+        let Ok(file) = dialog.open_future(Some(self)).await else {
+            return;
+        };
+
+        let calendar = file
+            .load_contents_future()
+            .await
+            .map_err(|err| err.to_string())
+            .and_then(|(contents, _)| {
+                ical::parse(&String::from_utf8_lossy(&contents), utc_to_local)
+                    .map_err(|err| err.to_string())
+            });
+        let calendar = match calendar {
+            Ok(calendar) if !calendar.tasks.is_empty() => calendar,
+            Ok(_) => {
+                let toast = adw::Toast::new("The file has no tasks");
+                self.imp().toast_overlay.add_toast(toast);
+                return;
+            }
+            Err(err) => {
+                glib::g_warning!(LOG_DOMAIN, "Could not import {}: {err}", file.uri());
+                let toast = adw::Toast::new("The file could not be imported");
+                self.imp().toast_overlay.add_toast(toast);
+                return;
+            }
+        };
+
+        let name = calendar.name.unwrap_or_else(|| {
+            file.basename()
+                .and_then(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+                .unwrap_or_else(|| "Imported Tasks".to_owned())
+        });
+        let n_tasks = calendar.tasks.len();
+        let Some(collection) = self.ask_import_collection(&name, n_tasks).await else {
+            return;
+        };
+
+        // Importing the same file twice gives two copies rather than clashing IDs
+        let tasks = calendar
+            .tasks
+            .into_iter()
+            .map(|task| TaskObject::from_task_data(task.with_new_ids()))
+            .collect::<Vec<_>>();
+        collection.tasks().extend_from_slice(&tasks);
+        self.set_current_collection(collection);
+    }
+
+    /// Asks whether imported tasks go into a new collection called `name`,
+    /// which is then created, or into the current one.
+    async fn ask_import_collection(&self, name: &str, n_tasks: usize) -> Option<CollectionObject> {
+        let cancel_response = "cancel";
+        let current_response = "current";
+        let new_response = "new";
+
+        let current = self.current_collection();
+        let dialog = adw::AlertDialog::builder()
+            .heading(match n_tasks {
+                1 => "Import 1 Task".to_owned(),
+                n => format!("Import {n} Tasks"),
+            })
+            .body(match &current {
+                Some(current) => format!(
+                    "Add them to “{}”, or to a new collection called “{name}”.",
+                    current.title()
+                ),
+                None => format!("They will be added to a new collection called “{name}”."),
+            })
+            .close_response(cancel_response)
+            .default_response(new_response)
+            .build();
+        dialog.add_response(cancel_response, "Cancel");
+        if current.is_some() {
+            dialog.add_response(current_response, "Add to _Current");
+        }
+        dialog.add_response(new_response, "_New Collection");
+        dialog.set_response_appearance(new_response, adw::ResponseAppearance::Suggested);
+
+        let response = dialog.choose_future(self).await;
+
+        if response == current_response {
+            current
+        } else if response == new_response {
+            let collection = CollectionObject::new(name, gio::ListStore::new::<TaskObject>());
+            self.collections().append(&collection);
+            Some(collection)
+        } else {
+            None
+        }
+    }
+
+    async fn export_collection(&self, collection: CollectionObject) {
+        let data = collection.to_collection_data();
+        self.export_tasks(&data.title, &data.tasks_data).await;
+    }
+
+    async fn export_all(&self) {
+        let tasks = self
+            .collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .flat_map(|collection| collection.to_collection_data().tasks_data)
+            .collect::<Vec<_>>();
+        self.export_tasks("To-Do", &tasks).await;
+    }
+
+    /// Asks where to save `tasks` and writes them there as a calendar called `name`.
+    async fn export_tasks(&self, name: &str, tasks: &[TaskData]) {
+        let dialog = gtk::FileDialog::builder()
+            .title("Export Tasks")
+            .initial_name(format!("{name}.ics"))
+            .filters(&Self::calendar_filters())
+            .build();
+        let Ok(file) = dialog.save_future(Some(self)).await else {
+            return;
+        };
+
+        let stamp = glib::real_time() / 1_000_000;
+        let contents = ical::export(name, tasks, stamp);
+        self.write_export(&file, contents).await;
+    }
+
+    /// Adds the tasks of a todo.txt file to the collections of their
+    /// projects, creating the ones that don't exist yet.
+    async fn import_todotxt(&self) {
+        let dialog = gtk::FileDialog::builder()
+            .title("Import todo.txt")
+            .filters(&Self::todotxt_filters())
+            .build();
+        let Ok(file) = dialog.open_future(Some(self)).await else {
+            return;
+        };
+
+        let contents = match file.load_contents_future().await {
+            Ok((contents, _)) => contents,
+            Err(err) => {
+                glib::g_warning!(LOG_DOMAIN, "Could not import {}: {err}", file.uri());
+                let toast = adw::Toast::new("The file could not be imported");
+                self.imp().toast_overlay.add_toast(toast);
+                return;
+            }
+        };
+        let projects = todotxt::parse(&String::from_utf8_lossy(&contents));
+        let n_tasks = projects
+            .iter()
+            .map(|project| project.tasks_data.len())
+            .sum::<usize>();
+        if n_tasks == 0 {
+            let toast = adw::Toast::new("The file has no tasks");
+            self.imp().toast_overlay.add_toast(toast);
+            return;
+        }
+
+        let collections = self.collections();
+        for project in projects {
+            // Tasks without a project go where new tasks go
+            let collection = if project.title.is_empty() {
+                self.new_task_collection()
+            } else {
+                collections
+                    .iter::<CollectionObject>()
+                    .filter_map(Result::ok)
+                    .find(|collection| todotxt::is_project(&collection.title(), &project.title))
+            };
+            let collection = collection.unwrap_or_else(|| {
+                let title = Some(project.title.as_str())
+                    .filter(|title| !title.is_empty())
+                    .unwrap_or("Imported Tasks");
+                let collection = CollectionObject::new(title, gio::ListStore::new::<TaskObject>());
+                collections.append(&collection);
+                collection
+            });
+
+            let tasks = project
+                .tasks_data
+                .into_iter()
+                .map(|task| TaskObject::from_task_data(task.with_new_ids()))
+                .collect::<Vec<_>>();
+            collection.tasks().extend_from_slice(&tasks);
+        }
+
+        let toast = adw::Toast::new(&match n_tasks {
+            1 => "Imported 1 task".to_owned(),
+            n => format!("Imported {n} tasks"),
+        });
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    /// Writes every collection to a todo.txt file, each one as a project.
+    async fn export_todotxt(&self) {
+        let dialog = gtk::FileDialog::builder()
+            .title("Export todo.txt")
+            .initial_name("todo.txt")
+            .filters(&Self::todotxt_filters())
+            .build();
+        let Ok(file) = dialog.save_future(Some(self)).await else {
+            return;
+        };
+
+        let collections = self
+            .collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .map(|collection| collection.to_collection_data())
+            .collect::<Vec<_>>();
+        let contents = todotxt::export(&collections, |unix| {
+            let date_time = glib::DateTime::from_unix_local(unix).ok()?;
+            Some(split_date_time(&date_time).0)
+        });
+        self.write_export(&file, contents).await;
+    }
+
+    async fn export_markdown(&self, collection: CollectionObject) {
+        let data = collection.to_collection_data();
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("Markdown"));
+        filter.add_mime_type("text/markdown");
+        filter.add_suffix("md");
+        let filters = gio::ListStore::new::<gtk::FileFilter>();
+        filters.append(&filter);
+
+        let dialog = gtk::FileDialog::builder()
+            .title("Export Markdown")
+            .initial_name(format!("{}.md", data.title))
+            .filters(&filters)
+            .build();
+        let Ok(file) = dialog.save_future(Some(self)).await else {
+            return;
+        };
+
+        self.write_export(&file, markdown::checklist(&data)).await;
+    }
+
+    fn copy_markdown(&self, collection: &CollectionObject) {
+        let text = markdown::checklist(&collection.to_collection_data());
+        self.clipboard().set_text(&text);
+
+        let toast = adw::Toast::new("Copied to clipboard");
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    /// Writes an export to `file`, telling whether that worked with a toast.
+    async fn write_export(&self, file: &gio::File, contents: String) {
+        let result = file
+            .replace_contents_future(
+                contents,
+                None,
+                false,
+                gio::FileCreateFlags::REPLACE_DESTINATION,
+            )
+            .await;
+
+        let toast = match result {
+            Ok(_) => adw::Toast::new("Tasks exported"),
+            Err((_, err)) => {
+                glib::g_warning!(LOG_DOMAIN, "Could not export to {}: {err}", file.uri());
+                adw::Toast::new("Tasks could not be exported")
+            }
+        };
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    fn todotxt_filters() -> gio::ListStore {
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("todo.txt"));
+        filter.add_mime_type("text/plain");
+        filter.add_suffix("txt");
+
+        let filters = gio::ListStore::new::<gtk::FileFilter>();
+        filters.append(&filter);
+        filters
+    }
+
+    fn calendar_filters() -> gio::ListStore {
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("iCalendar"));
+        filter.add_mime_type("text/calendar");
+        filter.add_suffix("ics");
+
+        let filters = gio::ListStore::new::<gtk::FileFilter>();
+        filters.append(&filter);
+        filters
+    }
+
+    /// The collection an action targets: the one with the given ID, or the
+    /// current one when the ID is empty.
+    fn action_collection(&self, id: &str) -> Option<CollectionObject> {
+        if id.is_empty() {
+            self.current_collection()
+        } else {
+            self.find_collection(id)
+        }
+    }
+
+    fn saved_filters(&self) -> gio::ListStore {
+        self.imp()
+            .saved_filters
+            .get()
+            .expect("Saved filters were not setup.")
+            .clone()
+    }
+
+    /// The store every collection lives in, which is saved automatically.
+    pub fn collections(&self) -> gio::ListStore {
+        self.imp()
+            .collections
+            .get()
+            .expect("Collections were not setup.")
+            .clone()
+    }
+
+    fn current_source(&self) -> CollectionSource {
+        self.imp()
+            .current_collection
+            .borrow()
+            .clone()
+            .expect("No collection selected")
+    }
+
+    /// The selected collection, `None` while a smart collection is shown.
+    fn current_collection(&self) -> Option<CollectionObject> {
+        match self.imp().current_collection.borrow().as_ref()? {
+            CollectionSource::Collection(collection) => Some(collection.clone()),
+            CollectionSource::Smart(_) | CollectionSource::SavedFilter(_) => None,
+        }
+    }
+
+    /// Where new tasks go: the current collection, or the default one while a
+    /// smart collection is shown.
+    fn new_task_collection(&self) -> Option<CollectionObject> {
+        self.current_collection().or_else(|| {
+            let id = PropertyGet::get(&self.settings(), |settings| {
+                settings.string("default-collection")
+            });
+            // Fall back to the first one, the default may have been deleted
+            self.find_collection(&id)
+                .or_else(|| self.collections().item(0).and_downcast())
+        })
+    }
+
+    /// Every task of every collection, in sidebar order.
+    fn all_tasks(&self) -> FlattenListModel {
+        self.imp()
+            .all_tasks
+            .get()
+            .expect("Smart collections were not setup.")
+            .clone()
+    }
+
+    /// Smart collection and saved filter queries look at things their filter
+    /// can't watch, like due dates, so it is run again after every edit.
+    fn refilter_current_source(&self) {
+        if let Some(filter) = self.imp().current_source_filter.borrow().as_ref() {
+            filter.changed(gtk::FilterChange::Different);
+        }
+    }
+
+    fn set_filter(&self) {
+        // Without a collection there is nothing to filter
+        if let Some(filter_model) = self.imp().current_filter_model.borrow().as_ref() {
+            filter_model.set_filter(Some(&self.task_filter()));
+        }
+    }
+
+    /// The search combined with the done/open filter.
+    fn task_filter(&self) -> gtk::EveryFilter {
+        let task_filter = gtk::EveryFilter::new();
+        task_filter.append(self.search_filter().clone());
+        if let Some(filter) = self.filter() {
+            task_filter.append(filter);
+        }
+        task_filter
+    }
+
+    fn search_filter(&self) -> &gtk::StringFilter {
+        self.imp()
+            .search_filter
+            .get()
+            .expect("Search was not setup.")
+    }
+
+    fn filter(&self) -> Option<CustomFilter> {
+        let filter_done = CustomFilter::new(|object| {
+            object
+                .downcast_ref::<TaskObject>()
+                .expect("Must be task object")
+                .is_completed()
+        });
+
+        let filter_open = CustomFilter::new(|object| {
+            !object
+                .downcast_ref::<TaskObject>()
+                .expect("Must be task object")
+                .is_completed()
+        });
+
+        let filter_state = PropertyGet::get(&self.settings(), |settings| settings.string("filter"));
+
+        match filter_state.as_str() {
+            "All" => None,
+            "Open" => Some(filter_done),
+            "Done" => Some(filter_open),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Sorts again after a change that the sorter can't notice by itself.
+    fn resort(&self) {
+        if let Some(sorter) = self
+            .imp()
+            .current_sort_model
+            .borrow()
+            .as_ref()
+            .and_then(SortListModel::sorter)
+        {
+            sorter.changed(gtk::SorterChange::Different);
+        }
+    }
+
+    fn set_sorter(&self) {
+        // Without a collection there is nothing to sort
+        if let Some(sort_model) = self.imp().current_sort_model.borrow().as_ref() {
+            sort_model.set_sorter(self.sorter().as_ref());
+        }
+    }
+
+    fn sorter(&self) -> Option<CustomSorter> {
+        let sort_state = PropertyGet::get(&self.settings(), |settings| settings.string("sort"));
+
+        // Newest and most urgent come first. The sort is stable, so ties keep
+        // their manual order
+        let compare: fn(&TaskObject, &TaskObject) -> Ordering = match sort_state.as_str() {
+            "Manual" => return None,
+            "Alphabetical" => |a, b| a.content().to_lowercase().cmp(&b.content().to_lowercase()),
+            "Created" => |a, b| b.created_at().cmp(&a.created_at()),
+            "Modified" => |a, b| b.modified_at().cmp(&a.modified_at()),
+            "Due" => |a, b| {
+                // Tasks without a due date go last
+                let (a, b) = (a.due(), b.due());
+                a.is_none().cmp(&b.is_none()).then(a.cmp(&b))
+            },
+            "Priority" => |a, b| b.priority().cmp(&a.priority()),
+            "CompletedLast" => |a, b| a.is_completed().cmp(&b.is_completed()),
+            _ => unreachable!(),
+        };
+
+        Some(CustomSorter::new(move |a, b| {
+            let a = a.downcast_ref::<TaskObject>().expect("Must be task object");
+            let b = b.downcast_ref::<TaskObject>().expect("Must be task object");
+            compare(a, b).into()
+        }))
+    }
+
+    fn set_stack(&self) {
+        if self.collections().n_items() > 0 {
+            self.imp().stack.set_visible_child_name("main");
+        } else {
+            self.imp().stack.set_visible_child_name("placeholder");
+        }
+    }
+
+    fn create_collection_row(&self, collection_object: &CollectionObject) -> gtk::ListBoxRow {
+        let label = gtk::Label::builder()
+            .ellipsize(pango::EllipsizeMode::End)
+            .xalign(0.0)
+            .build();
+
+        collection_object
+            .bind_property("title", &label, "label")
+            .sync_create()
+            .build();
+
+        let row = gtk::ListBoxRow::builder()
+            .child(&label)
+            .action_name("win.select-collection")
+            .action_target(&collection_object.id().to_variant())
+            .build();
+
+        row.add_controller(Self::create_drag_source(
+            row.upcast_ref(),
+            &collection_object.to_value(),
+        ));
+
+        // Tasks dropped on a collection move into it, collections swap places
+        let drop_target = gtk::DropTarget::new(glib::Type::INVALID, gdk::DragAction::MOVE);
+        drop_target.set_types(&[TaskObject::static_type(), CollectionObject::static_type()]);
+        drop_target.connect_drop(clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            collection_object,
+            #[upgrade_or]
+            false,
+            move |_, value, _, _| {
+                if let Ok(task) = value.get::<TaskObject>() {
+                    collection_object.find_task(&task.id()).is_none()
+                        && window.move_task(&task, &collection_object, None)
+                } else if let Ok(collection) = value.get::<CollectionObject>() {
+                    window.move_collection(&collection, &collection_object)
+                } else {
+                    false
+                }
+            }
+        ));
+        row.add_controller(drop_target);
+
+        row
+    }
+
+    /// A drag source that carries `value` and shows `widget` as the drag icon.
+    fn create_drag_source(widget: &gtk::Widget, value: &glib::Value) -> gtk::DragSource {
+        let drag_source = gtk::DragSource::builder()
+            .actions(gdk::DragAction::MOVE)
+            .content(&gdk::ContentProvider::for_value(value))
+            .build();
+
+        drag_source.connect_drag_begin(clone!(
+            #[weak]
+            widget,
+            move |drag_source, _| {
+                let paintable = gtk::WidgetPaintable::new(Some(&widget));
+                drag_source.set_icon(Some(&paintable), 0, 0);
+            }
+        ));
+
+        drag_source
+    }
+
+    /// Moves `task` out of whichever collection holds it and into `collection`
+    /// at `position`, or at the end without one.
+    fn move_task(
+        &self,
+        task: &TaskObject,
+        collection: &CollectionObject,
+        position: Option<u32>,
+    ) -> bool {
+        let Some((_, source, source_position)) = self.locate_task(&task.id()) else {
+            return false;
+        };
+        source.remove(source_position);
+
+        // After the removal, inserting at the target's old position puts the
+        // task before the target when moving up and after it when moving down
+        let tasks = collection.tasks();
+        let position = position.map_or(tasks.n_items(), |position| position.min(tasks.n_items()));
+        tasks.insert(position, task);
+        true
+    }
+
+    fn move_collection(&self, collection: &CollectionObject, target: &CollectionObject) -> bool {
+        let collections = self.collections();
+        let (Some(from), Some(to)) = (collections.find(collection), collections.find(target))
+        else {
+            return false;
+        };
+        if from == to {
+            return false;
+        }
+
+        collections.remove(from);
+        collections.insert(to, collection);
+
+        // The moved row was recreated, so the selection has to be restored
+        if self.imp().current_collection.borrow().is_some() {
+            self.select_collection_row();
+        }
+        true
+    }
+
+    fn select_collection_row(&self) {
+        let imp = self.imp();
+        let (list, index) = match self.current_source() {
+            CollectionSource::Collection(collection) => {
+                (&imp.collections_list, self.collections().find(&collection))
+            }
+            CollectionSource::Smart(smart) => (
+                &imp.smart_collections_list,
+                SmartCollection::ALL
+                    .iter()
+                    .position(|other| *other == smart)
+                    .map(|index| index as u32),
+            ),
+            CollectionSource::SavedFilter(saved_filter) => (
+                &imp.saved_filters_list,
+                self.saved_filters().find(&saved_filter),
+            ),
+        };
+
+        // Only one row in the whole sidebar is selected
+        imp.smart_collections_list.unselect_all();
+        imp.collections_list.unselect_all();
+        imp.saved_filters_list.unselect_all();
+        if let Some(index) = index {
+            let row = list.row_at_index(index as i32);
+            list.select_row(row.as_ref());
+        }
+    }
+
+    /// Shows either the current collection, the results of searching all
+    /// collections, or the tasks with the selected tag, in that priority.
+    fn update_task_view(&self) {
+        let imp = self.imp();
+        let text = imp.search_entry.text();
+        self.search_filter().set_search(Some(text.as_str()));
+
+        if imp.search_all_button.is_active() && !text.is_empty() {
+            let text = text.to_lowercase();
+            self.show_matching_tasks(|task| task.content().to_lowercase().contains(&text));
+            imp.tasks_stack.set_visible_child_name("search");
+        } else if let Some(tag) = imp.current_tag.borrow().as_deref() {
+            self.show_matching_tasks(|task| task.has_tag(tag));
+            imp.tasks_stack.set_visible_child_name("search");
+        } else {
+            imp.tasks_stack.set_visible_child_name("tasks");
+        }
+    }
+
+    /// Lists the tasks of every collection that `matches`, grouped by collection.
+    fn show_matching_tasks(&self, matches: impl Fn(&TaskObject) -> bool) {
+        let search_results = &self.imp().search_results;
+        while let Some(child) = search_results.first_child() {
+            search_results.remove(&child);
+        }
+
+        for collection in self
+            .collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+        {
+            let matches = collection
+                .tasks()
+                .iter::<TaskObject>()
+                .filter_map(Result::ok)
+                .filter(&matches)
+                .collect::<Vec<_>>();
+            if matches.is_empty() {
+                continue;
+            }
+
+            let heading = gtk::Label::builder()
+                .label(collection.title())
+                .ellipsize(pango::EllipsizeMode::End)
+                .xalign(0.0)
+                .build();
+            heading.add_css_class("heading");
+
+            let list = gtk::ListBox::builder()
+                .selection_mode(gtk::SelectionMode::None)
+                .build();
+            list.add_css_class("boxed-list");
+            for task in &matches {
+                list.append(&self.create_search_result_row(task));
+            }
+
+            let group = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(6)
+                .build();
+            group.append(&heading);
+            group.append(&list);
+            search_results.append(&group);
+        }
+
+        if search_results.first_child().is_none() {
+            let status_page = adw::StatusPage::builder()
+                .icon_name("system-search-symbolic")
+                .title("No Results")
+                .build();
+            search_results.append(&status_page);
+        }
+    }
+
+    fn create_search_result_row(&self, task: &TaskObject) -> adw::ActionRow {
+        let row = adw::ActionRow::builder()
+            .title(task.content())
+            .use_markup(false)
+            .activatable(true)
+            .build();
+        if let Some(due_label) = task.due_label() {
+            row.set_subtitle(&due_label);
+        }
+        if task.is_completed() {
+            row.add_prefix(&gtk::Image::from_icon_name("object-select-symbolic"));
+        }
+
+        row.connect_activated(clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            task,
+            move |_| {
+                window.reveal_task(&task.id());
+            }
+        ));
+
+        row
+    }
+
+    /// Leaves the search and shows the task with `id` in its collection.
+    fn reveal_task(&self, id: &str) {
+        let Some((collection, _, _)) = self.locate_task(id) else {
+            return;
+        };
+        let Some(task) = collection.find_task(id) else {
+            return;
+        };
+
+        let imp = self.imp();
+        imp.search_bar.set_search_mode(false);
+        imp.search_entry.set_text("");
+        self.clear_current_tag();
+        self.update_task_view();
+
+        if self.current_collection().as_ref() != Some(&collection) {
+            self.set_current_collection(collection);
+        }
+        imp.split_view.set_show_content(true);
+
+        let position = || {
+            imp.current_sort_model
+                .borrow()
+                .as_ref()?
+                .iter::<TaskObject>()
+                .filter_map(Result::ok)
+                .position(|item| item == task)
+        };
+        // The done/open filter may hide the task, so fall back to showing all
+        let position = position().or_else(|| {
+            self.settings()
+                .set_string("filter", "All")
+                .expect("Could not set setting.");
+            position()
+        });
 
-        self.imp().split_view.set_show_content(true);
+        if let Some(row) =
+            position.and_then(|position| imp.tasks_list.row_at_index(position as i32))
+        {
+            row.grab_focus();
+        }
     }
 
-    fn collections(&self) -> gio::ListStore {
-        self.imp()
-            .collections
-            .get()
-            .expect("Collections were not setup.")
-            .clone()
+    fn select_tag(&self, tag: String) {
+        let imp = self.imp();
+        imp.current_tag.replace(Some(tag));
+        imp.smart_collections_list.unselect_all();
+        imp.collections_list.unselect_all();
+        imp.saved_filters_list.unselect_all();
+        self.select_tag_row();
+        self.update_task_view();
+        imp.split_view.set_show_content(true);
     }
 
-    fn current_collection(&self) -> CollectionObject {
-        self.imp()
-            .current_collection
-            .borrow()
-            .clone()
-            .expect("No collection selected")
+    fn clear_current_tag(&self) {
+        let imp = self.imp();
+        imp.current_tag.replace(None);
+        imp.tags_list.unselect_all();
     }
 
-    fn set_filter(&self) {
-        self.imp()
-            .current_filter_model
-            .borrow()
-            .clone()
-            .expect("No filter model")
-            .set_filter(self.filter().as_ref());
+    fn select_tag_row(&self) {
+        let imp = self.imp();
+        let current_tag = imp.current_tag.borrow();
+        let mut index = 0;
+        while let Some(row) = imp.tags_list.row_at_index(index) {
+            let tag = row
+                .action_target_value()
+                .and_then(|target| target.get::<String>());
+            if tag.is_some() && tag == *current_tag {
+                imp.tags_list.select_row(Some(&row));
+                return;
+            }
+            index += 1;
+        }
     }
 
-    fn filter(&self) -> Option<CustomFilter> {
-        let filter_done = CustomFilter::new(|object| {
-            object
-                .downcast_ref::<TaskObject>()
-                .expect("Must be task object")
-                .is_completed()
-        });
-
-        let filter_open = CustomFilter::new(|object| {
-            !object
-                .downcast_ref::<TaskObject>()
-                .expect("Must be task object")
-                .is_completed()
-        });
-
-        let filter_state = PropertyGet::get(&self.settings(), |settings| settings.string("filter"));
-
-        match filter_state.as_str() {
-            "All" => None,
-            "Open" => Some(filter_done),
-            "Done" => Some(filter_open),
-            _ => unreachable!(),
+    /// Lists every tag in use in the sidebar, with how many tasks have it.
+    fn refresh_tags(&self) {
+        let mut counts = BTreeMap::<String, usize>::new();
+        for collection in self
+            .collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+        {
+            for task in collection
+                .tasks()
+                .iter::<TaskObject>()
+                .filter_map(Result::ok)
+            {
+                for tag in task.tags() {
+                    *counts.entry(tag).or_default() += 1;
+                }
+            }
         }
-    }
 
-    fn set_stack(&self) {
-        if self.collections().n_items() > 0 {
-            self.imp().stack.set_visible_child_name("main");
-        } else {
-            self.imp().stack.set_visible_child_name("placeholder");
+        let imp = self.imp();
+        imp.tags_list.remove_all();
+        for (tag, count) in &counts {
+            imp.tags_list.append(&Self::create_tag_row(tag, *count));
+        }
+        imp.tags_section.set_visible(!counts.is_empty());
+
+        // The selected tag may have just been removed from its last task
+        let current_tag = imp.current_tag.borrow().clone();
+        if let Some(tag) = current_tag {
+            if counts.contains_key(&tag) {
+                self.select_tag_row();
+            } else {
+                self.clear_current_tag();
+            }
+            self.update_task_view();
         }
     }
 
-    fn create_collection_row(&self, collection_object: &CollectionObject) -> gtk::ListBoxRow {
+    fn create_tag_row(tag: &str, count: usize) -> gtk::ListBoxRow {
         let label = gtk::Label::builder()
+            .label(format!("#{tag}"))
             .ellipsize(pango::EllipsizeMode::End)
             .xalign(0.0)
+            .hexpand(true)
             .build();
 
-        collection_object
-            .bind_property("title", &label, "label")
-            .sync_create()
-            .build();
+        let count_label = gtk::Label::new(Some(&count.to_string()));
+        count_label.add_css_class("dim-label");
 
-        gtk::ListBoxRow::builder().child(&label).build()
-    }
+        let content = gtk::Box::builder().spacing(6).build();
+        content.append(&label);
+        content.append(&count_label);
 
-    fn select_collection_row(&self) {
-        if let Some(index) = self.collections().find(&self.current_collection()) {
-            let row = self.imp().collections_list.row_at_index(index as i32);
-            self.imp().collections_list.select_row(row.as_ref());
-        }
+        gtk::ListBoxRow::builder()
+            .child(&content)
+            .action_name("win.select-tag")
+            .action_target(&tag.to_variant())
+            .build()
     }
 
-    fn set_task_list_visibility(&self, tasks: &gio::ListStore) {
+    fn set_task_list_visibility(&self, tasks: &impl IsA<gio::ListModel>) {
         // Assure that the task list is only visible when there is at least one task
         self.imp().tasks_list.set_visible(tasks.n_items() > 0);
     }
 
-    fn restore_data(&self) {
-        if let Ok(file) = std::fs::File::open(data_path()) {
-            let data: Vec<CollectionData> =
-                serde_json::from_reader(file).expect("Could not read file");
+    /// Replaces the collections and saved filters with those in the data
+    /// file, leaving them alone if it can't be read. Anything made while it
+    /// couldn't be read was never saved, and would otherwise end up next to
+    /// the restored data.
+    fn restore_data(&self) -> Result<(), persistence::LoadError> {
+        let data = persistence::load()?;
+
+        let saved_filters = data
+            .saved_filters
+            .into_iter()
+            .map(SavedFilterObject::from_saved_filter_data)
+            .collect::<Vec<SavedFilterObject>>();
+        let saved_filters_store = self.saved_filters();
+        saved_filters_store.splice(0, saved_filters_store.n_items(), &saved_filters);
+
+        let collections = data
+            .collections
+            .into_iter()
+            .map(CollectionObject::from_collection_data)
+            .collect::<Vec<CollectionObject>>();
+
+        let collections_store = self.collections();
+        collections_store.splice(0, collections_store.n_items(), &collections);
 
-            let collections = data
-                .into_iter()
-                .map(CollectionObject::from_collection_data)
-                .collect::<Vec<CollectionObject>>();
+        // The current collection, if any, was just replaced
+        match collections.first() {
+            Some(collection) => self.set_current_collection(collection.clone()),
+            None => self.clear_current_collection(),
+        }
+        Ok(())
+    }
 
-            self.collections().extend_from_slice(&collections);
+    /// Deals with a data file that could not be restored. Saving stays off
+    /// for as long as writing would lose what is in it.
+    fn recover_data_file(&self, err: &persistence::LoadError) {
+        glib::g_warning!(LOG_DOMAIN, "Could not restore collections: {err}");
+
+        if err.is_from_newer_version() {
+            self.autosave().set_enabled(false);
+            let dialog = adw::AlertDialog::builder()
+                .heading("Tasks Created by a Newer Version")
+                .body(
+                    "Your saved tasks were created by a newer version of To-Do. They are \
+                     left untouched, and changes made here will not be saved.",
+                )
+                .build();
+            dialog.add_response("close", "_OK");
+            dialog.present(Some(self));
+            return;
+        }
 
-            if let Some(collection) = collections.first() {
-                self.set_current_collection(collection.clone());
+        match persistence::move_aside() {
+            Ok(backup_path) => {
+                let toast = adw::Toast::builder()
+                    .title("Saved tasks could not be read and were backed up")
+                    .button_label("Open Backup")
+                    .action_name("win.open-backup")
+                    .action_target(&backup_path.to_string_lossy().to_variant())
+                    .timeout(0)
+                    .build();
+                self.imp().toast_overlay.add_toast(toast);
+            }
+            Err(err) => {
+                glib::g_warning!(LOG_DOMAIN, "Could not back up data file: {err}");
+                // The unreadable file is still where snapshots go
+                self.autosave().set_enabled(false);
+                glib::spawn_future_local(clone!(
+                    #[weak(rename_to = window)]
+                    self,
+                    async move {
+                        window.ask_recover_data_file().await;
+                    }
+                ));
             }
         }
     }
 
-    fn set_current_collection(&self, collection: CollectionObject) {
-        let tasks = collection.tasks();
-        let filter_model = FilterListModel::new(Some(tasks.clone()), self.filter());
-        let selection_model = NoSelection::new(Some(filter_model.clone()));
+    /// Asks whether to read the data file again or to replace it, for when it
+    /// could neither be read nor backed up.
+    async fn ask_recover_data_file(&self) {
+        let continue_response = "continue";
+        let retry_response = "retry";
+        let fresh_response = "fresh";
+
+        loop {
+            let dialog = adw::AlertDialog::builder()
+                .heading("Saved Tasks Could Not Be Read")
+                .body(
+                    "The data file could not be read or backed up, so nothing is saved \
+                     until you decide what to do with it. Starting fresh replaces it.",
+                )
+                .close_response(continue_response)
+                .default_response(retry_response)
+                .build();
+            dialog.add_responses(&[
+                (continue_response, "_Continue Without Saving"),
+                (retry_response, "_Try Again"),
+                (fresh_response, "_Start Fresh"),
+            ]);
+            dialog.set_response_appearance(fresh_response, adw::ResponseAppearance::Destructive);
+
+            let response = dialog.choose_future(self).await;
+
+            if response == retry_response {
+                match self.restore_data() {
+                    Ok(()) => self.autosave().set_enabled(true),
+                    Err(err) => {
+                        glib::g_warning!(LOG_DOMAIN, "Could not restore collections: {err}");
+                        continue;
+                    }
+                }
+            } else if response == fresh_response {
+                self.autosave().set_enabled(true);
+            } else {
+                let toast = adw::Toast::new("Changes will not be saved");
+                self.imp().toast_overlay.add_toast(toast);
+            }
+            return;
+        }
+    }
+
+    fn open_backup(&self, path: &Path) {
+        let file = gio::File::for_path(path);
+        gtk::FileLauncher::new(Some(&file)).open_containing_folder(
+            Some(self),
+            gio::Cancellable::NONE,
+            clone!(
+                #[weak(rename_to = window)]
+                self,
+                move |result| {
+                    if let Err(err) = result {
+                        glib::g_warning!(LOG_DOMAIN, "Could not open backup: {err}");
+                        let toast = adw::Toast::new("The backup could not be opened");
+                        window.imp().toast_overlay.add_toast(toast);
+                    }
+                }
+            ),
+        );
+    }
+
+    async fn confirm_close_without_saving(&self, err: &io::Error) {
+        let cancel_response = "cancel";
+        let close_response = "close";
+
+        let dialog = adw::AlertDialog::builder()
+            .heading("Could Not Save Tasks")
+            .body(format!(
+                "Your latest changes could not be written to disk: {err}"
+            ))
+            .close_response(cancel_response)
+            .default_response(cancel_response)
+            .build();
+        dialog.add_responses(&[
+            (cancel_response, "Cancel"),
+            (close_response, "Close Without Saving"),
+        ]);
+        dialog.set_response_appearance(close_response, adw::ResponseAppearance::Destructive);
+
+        let response = dialog.choose_future(self).await;
+
+        if response == close_response {
+            self.imp().close_without_saving.set(true);
+            self.close();
+        }
+    }
+
+    /// Shows `source` in the task list: a collection, or a smart collection
+    /// or saved filter that picks tasks out of all of them.
+    fn set_current_collection(&self, source: impl Into<CollectionSource>) {
+        let source = source.into();
+        let source_filter = Self::source_filter(&source);
+        let tasks: gio::ListModel = match &source {
+            CollectionSource::Collection(collection) => collection.tasks().upcast(),
+            _ => FilterListModel::new(Some(self.all_tasks()), source_filter.clone()).upcast(),
+        };
+        self.imp().current_source_filter.replace(source_filter);
+        let filter_model = FilterListModel::new(Some(tasks.clone()), Some(self.task_filter()));
+        let sort_model = SortListModel::new(Some(filter_model.clone()), self.sorter());
+        let selection_model = NoSelection::new(Some(sort_model.clone()));
 
         self.imp().tasks_list.bind_model(
             Some(&selection_model),
@@ -334,30 +2782,74 @@ impl Window {
             ),
         );
 
-        // Store filter model
+        // Store filter and sort models
         self.imp().current_filter_model.replace(Some(filter_model));
+        self.imp().current_sort_model.replace(Some(sort_model));
 
         // If present, disconnect old `tasks_changed` handler
-        if let Some(handler_id) = self.imp().tasks_changed_handler_id.take() {
-            self.tasks().disconnect(handler_id);
+        if let Some((tasks, handler_id)) = self.imp().tasks_changed_handler.take() {
+            tasks.disconnect(handler_id);
         }
 
         // Set current tasks
-        self.imp().current_collection.replace(Some(collection));
+        self.imp().current_collection.replace(Some(source));
 
         self.select_collection_row();
+        self.clear_current_tag();
+        self.update_task_view();
 
         self.set_task_list_visibility(&tasks);
-        let tasks_changed_handler_id = self.tasks().connect_items_changed(clone!(
+        let tasks_changed_handler_id = tasks.connect_items_changed(clone!(
             #[weak(rename_to = window)]
             self,
             move |tasks, _, _, _| {
-                window.set_task_list_visibility(&tasks);
+                window.set_task_list_visibility(tasks);
             }
         ));
         self.imp()
-            .tasks_changed_handler_id
-            .replace(Some(tasks_changed_handler_id));
+            .tasks_changed_handler
+            .replace(Some((tasks, tasks_changed_handler_id)));
+    }
+
+    /// Picks the tasks of `source` out of all tasks, `None` for a collection,
+    /// which has its own.
+    fn source_filter(source: &CollectionSource) -> Option<CustomFilter> {
+        match source {
+            CollectionSource::Collection(_) => None,
+            CollectionSource::Smart(smart) => {
+                let smart = *smart;
+                Some(CustomFilter::new(move |object| {
+                    let task = object
+                        .downcast_ref::<TaskObject>()
+                        .expect("Must be task object");
+                    let now = glib::DateTime::now_local().expect("Could not get the local time");
+                    smart.contains(task, &now)
+                }))
+            }
+            // A query that no longer parses shows nothing rather than everything
+            CollectionSource::SavedFilter(saved_filter) => Some(
+                saved_filter
+                    .parsed_query()
+                    .map_or_else(|| CustomFilter::new(|_| false), Query::into_filter),
+            ),
+        }
+    }
+
+    fn clear_current_collection(&self) {
+        if let Some((tasks, handler_id)) = self.imp().tasks_changed_handler.take() {
+            tasks.disconnect(handler_id);
+        }
+
+        self.imp().current_collection.replace(None);
+        self.imp().current_source_filter.replace(None);
+        self.imp().current_filter_model.replace(None);
+        self.imp().current_sort_model.replace(None);
+        self.imp()
+            .tasks_list
+            .bind_model(None::<&gio::ListModel>, |_| -> gtk::Widget {
+                unreachable!("No model is bound")
+            });
+        self.imp().split_view.set_show_content(false);
     }
 
     fn setup_collections(&self) {
@@ -385,7 +2877,242 @@ impl Window {
         );
     }
 
+    fn setup_saved_filters(&self) {
+        let saved_filters = gio::ListStore::new::<SavedFilterObject>();
+        self.imp()
+            .saved_filters
+            .set(saved_filters.clone())
+            .expect("Saved filters already set.");
+
+        self.imp().saved_filters_list.bind_model(
+            Some(&saved_filters),
+            clone!(
+                #[weak(rename_to = window)]
+                self,
+                #[upgrade_or_panic]
+                move |obj| {
+                    let saved_filter = obj
+                        .downcast_ref::<SavedFilterObject>()
+                        .expect("Must be saved filter object");
+
+                    let row = window.create_saved_filter_row(saved_filter);
+                    row.upcast()
+                }
+            ),
+        );
+
+        saved_filters.connect_items_changed(clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |saved_filters, _, _, _| {
+                window
+                    .imp()
+                    .saved_filters_section
+                    .set_visible(saved_filters.n_items() > 0);
+            }
+        ));
+    }
+
+    fn create_saved_filter_row(&self, saved_filter: &SavedFilterObject) -> gtk::ListBoxRow {
+        let label = gtk::Label::builder()
+            .ellipsize(pango::EllipsizeMode::End)
+            .xalign(0.0)
+            .build();
+        saved_filter
+            .bind_property("name", &label, "label")
+            .sync_create()
+            .build();
+
+        let content = gtk::Box::builder().spacing(12).build();
+        content.append(&gtk::Image::from_icon_name("edit-find-symbolic"));
+        content.append(&label);
+
+        let row = gtk::ListBoxRow::builder()
+            .child(&content)
+            .action_name("win.select-saved-filter")
+            .action_target(&saved_filter.id().to_variant())
+            .build();
+        saved_filter
+            .bind_property("query", &row, "tooltip-text")
+            .sync_create()
+            .build();
+
+        row
+    }
+
+    fn setup_smart_collections(&self) {
+        let collection_tasks = MapListModel::new(Some(self.collections()), |object| {
+            object
+                .downcast_ref::<CollectionObject>()
+                .expect("Must be collection object")
+                .tasks()
+                .upcast::<glib::Object>()
+        });
+        self.imp()
+            .all_tasks
+            .set(FlattenListModel::new(Some(collection_tasks)))
+            .expect("Smart collections already set.");
+
+        for smart in SmartCollection::ALL {
+            self.imp()
+                .smart_collections_list
+                .append(&Self::create_smart_collection_row(smart));
+        }
+
+        self.autosave().connect_changed(clone!(
+            #[weak(rename_to = window)]
+            self,
+            move || {
+                window.refilter_current_source();
+            }
+        ));
+    }
+
+    fn create_smart_collection_row(smart: SmartCollection) -> gtk::ListBoxRow {
+        let label = gtk::Label::builder()
+            .label(smart.title())
+            .ellipsize(pango::EllipsizeMode::End)
+            .xalign(0.0)
+            .build();
+
+        let content = gtk::Box::builder().spacing(12).build();
+        content.append(&gtk::Image::from_icon_name(smart.icon_name()));
+        content.append(&label);
+
+        gtk::ListBoxRow::builder()
+            .child(&content)
+            .action_name("win.select-smart-collection")
+            .action_target(&smart.id().to_variant())
+            .build()
+    }
+
+    fn setup_tags(&self) {
+        self.refresh_tags();
+        self.autosave().connect_changed(clone!(
+            #[weak(rename_to = window)]
+            self,
+            move || {
+                window.refresh_tags();
+            }
+        ));
+    }
+
+    fn setup_search(&self) {
+        let imp = self.imp();
+        imp.search_bar.connect_entry(&*imp.search_entry);
+
+        let search_filter = gtk::StringFilter::builder()
+            .expression(gtk::PropertyExpression::new(
+                TaskObject::static_type(),
+                None::<gtk::Expression>,
+                "content",
+            ))
+            .match_mode(gtk::StringFilterMatchMode::Substring)
+            .ignore_case(true)
+            .build();
+        imp.search_filter
+            .set(search_filter)
+            .expect("Search filter already set.");
+    }
+
     fn setup_callbacks(&self) {
+        let imp = self.imp();
+        imp.search_entry.connect_search_changed(clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| {
+                window.update_task_view();
+            }
+        ));
+        imp.search_all_button.connect_toggled(clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| {
+                window.update_task_view();
+            }
+        ));
+        imp.search_bar.connect_search_mode_enabled_notify(clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |search_bar| {
+                if !search_bar.is_search_mode() {
+                    window.imp().search_all_button.set_active(false);
+                }
+            }
+        ));
+
+        let task_menu_gesture = gtk::GestureClick::builder()
+            .button(gdk::BUTTON_SECONDARY)
+            .build();
+        task_menu_gesture.connect_pressed(clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |gesture, _, x, y| {
+                let Some(row) = window.imp().tasks_list.row_at_y(y as i32) else {
+                    return;
+                };
+                // Rows are bound to the sort model, so the row index is a position in it
+                let task = window
+                    .imp()
+                    .current_sort_model
+                    .borrow()
+                    .as_ref()
+                    .and_then(|model| model.item(row.index() as u32))
+                    .and_downcast::<TaskObject>();
+                if let Some(task) = task {
+                    gesture.set_state(gtk::EventSequenceState::Claimed);
+                    window.show_task_menu(&task, x, y);
+                }
+            }
+        ));
+        self.imp().tasks_list.add_controller(task_menu_gesture);
+
+        let collection_menu_gesture = gtk::GestureClick::builder()
+            .button(gdk::BUTTON_SECONDARY)
+            .build();
+        collection_menu_gesture.connect_pressed(clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |gesture, _, x, y| {
+                let id = window
+                    .imp()
+                    .collections_list
+                    .row_at_y(y as i32)
+                    .and_then(|row| row.action_target_value())
+                    .and_then(|target| target.get::<String>());
+                if let Some(id) = id {
+                    gesture.set_state(gtk::EventSequenceState::Claimed);
+                    window.show_collection_menu(&id, x, y);
+                }
+            }
+        ));
+        self.imp()
+            .collections_list
+            .add_controller(collection_menu_gesture);
+
+        let saved_filter_menu_gesture = gtk::GestureClick::builder()
+            .button(gdk::BUTTON_SECONDARY)
+            .build();
+        saved_filter_menu_gesture.connect_pressed(clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |gesture, _, x, y| {
+                let id = window
+                    .imp()
+                    .saved_filters_list
+                    .row_at_y(y as i32)
+                    .and_then(|row| row.action_target_value())
+                    .and_then(|target| target.get::<String>());
+                if let Some(id) = id {
+                    gesture.set_state(gtk::EventSequenceState::Claimed);
+                    window.show_saved_filter_menu(&id, x, y);
+                }
+            }
+        ));
+        self.imp()
+            .saved_filters_list
+            .add_controller(saved_filter_menu_gesture);
+
         self.settings().connect_changed(
             Some("filter"),
             clone!(
@@ -397,6 +3124,17 @@ impl Window {
             ),
         );
 
+        self.settings().connect_changed(
+            Some("sort"),
+            clone!(
+                #[weak(rename_to = window)]
+                self,
+                move |_, _| {
+                    window.set_sorter();
+                }
+            ),
+        );
+
         self.set_stack();
         self.collections().connect_items_changed(clone!(
             #[weak(rename_to = window)]
@@ -406,22 +3144,27 @@ impl Window {
             }
         ));
 
-        self.imp().collections_list.connect_row_activated(clone!(
+        self.imp().entry.connect_changed(clone!(
             #[weak(rename_to = window)]
             self,
-            move |_, row| {
-                let index = row.index();
-                let selection_collection = window
-                    .collections()
-                    .item(index as u32)
-                    .expect("Collection not found")
-                    .downcast::<CollectionObject>()
-                    .expect("Must be collection object");
-                window.set_current_collection(selection_collection);
-                window.imp().split_view.set_show_content(true);
+            move |_| {
+                window.update_quick_add_preview();
             }
         ));
 
+        // Entries keep pasted newlines, so a checklist arrives in one insertion
+        if let Some(delegate) = self.imp().entry.delegate() {
+            delegate.connect_insert_text(clone!(
+                #[weak(rename_to = window)]
+                self,
+                move |delegate, text, _| {
+                    if window.paste_checklist(text) {
+                        delegate.stop_signal_emission_by_name("insert-text");
+                    }
+                }
+            ));
+        }
+
         self.imp().entry.connect_activate(glib::clone!(
             #[weak(rename_to = window)]
             self,
@@ -439,6 +3182,69 @@ impl Window {
         ));
     }
 
+    fn autosave(&self) -> &Autosave {
+        self.imp().autosave.get().expect("Autosave was not setup.")
+    }
+
+    fn setup_autosave(&self) {
+        let autosave = Autosave::new(&self.collections(), &self.saved_filters());
+        autosave.connect_save_error(clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| {
+                let toast = adw::Toast::new("Tasks could not be saved");
+                window.imp().toast_overlay.add_toast(toast);
+            }
+        ));
+        self.imp()
+            .autosave
+            .set(autosave)
+            .unwrap_or_else(|_| panic!("Autosave already set."));
+    }
+
+    fn reminders(&self) -> &Reminders<SystemClock> {
+        self.imp()
+            .reminders
+            .get()
+            .expect("Reminders were not setup.")
+    }
+
+    fn setup_reminders(&self) {
+        let reminders = Reminders::new(
+            &self.collections(),
+            SystemClock,
+            clone!(
+                #[weak(rename_to = window)]
+                self,
+                move |task| {
+                    window.send_due_notification(task);
+                }
+            ),
+        );
+        self.imp()
+            .reminders
+            .set(reminders)
+            .unwrap_or_else(|_| panic!("Reminders already set."));
+    }
+
+    fn send_due_notification(&self, task: &TaskObject) {
+        let Some(app) = self.application() else {
+            return;
+        };
+
+        let id = task.id();
+        let notification = gio::Notification::new(&task.content());
+        notification.set_body(task.due_label().as_deref());
+        notification.add_button_with_target_value(
+            "Mark Done",
+            "app.mark-done",
+            Some(&id.to_variant()),
+        );
+        notification.add_button_with_target_value("Snooze", "app.snooze", Some(&id.to_variant()));
+
+        app.send_notification(Some(&id), &notification);
+    }
+
     fn setup_settings(&self) {
         let settings = gio::Settings::new(crate::APP_ID);
         self.imp()
@@ -451,6 +3257,24 @@ impl Window {
         let action_filter = self.settings().create_action("filter");
         self.add_action(&action_filter);
 
+        let action_toggle_search = gio::PropertyAction::new(
+            "toggle-search",
+            &*self.imp().search_bar,
+            "search-mode-enabled",
+        );
+        self.add_action(&action_toggle_search);
+
+        let action_sort = self.settings().create_action("sort");
+        self.add_action(&action_sort);
+
+        let action_default_collection = self.settings().create_action("default-collection");
+        self.add_action(&action_default_collection);
+
+        let action_complete_parent = self
+            .settings()
+            .create_action("complete-parent-with-subtasks");
+        self.add_action(&action_complete_parent);
+
         let action_close = gio::ActionEntry::builder("close")
             .activate(|window: &Window, _, _| {
                 window.close();